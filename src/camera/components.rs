@@ -0,0 +1,128 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Controls how the [`MainCamera`](crate::MainCamera) follows the players.
+#[derive(Component, Clone, Debug)]
+pub struct CameraRig {
+    /// How quickly the camera catches up with its target, higher is snappier
+    pub smoothing: f32,
+    /// How far the players may move horizontally from the focus before the camera follows
+    pub dead_zone_half_width: f32,
+    /// Seconds of horizontal player velocity the camera looks ahead
+    pub look_ahead_time: f32,
+    pub max_look_ahead: f32,
+    /// Shows a bit more above the players than below them
+    pub vertical_offset: f32,
+    /// The projection scale used when a single player is in view
+    pub zoom: f32,
+    /// Space kept around the players when framing several of them, in world units
+    pub framing_margin: Vec2,
+    /// The point the camera is centered on before look-ahead, `None` until it found a player
+    pub focus: Option<Vec2>,
+    pub look_ahead: f32,
+    /// How quickly the rig blends into and out of [`CameraZone`](crate::world::components::CameraZone) settings
+    pub zone_blend_speed: f32,
+    /// The zoom after blending in the active camera zone
+    pub blended_zoom: f32,
+    pub blended_offset: Vec2,
+    /// How much each axis is pinned to `axis_lock_center`, from `0.0` to `1.0`
+    pub axis_lock_weights: Vec2,
+    pub axis_lock_center: Vec2,
+    /// The screen shake applied on top of the followed position
+    pub shake_offset: Vec2,
+}
+
+impl Default for CameraRig {
+    fn default() -> Self {
+        CameraRig {
+            smoothing: 8.0,
+            dead_zone_half_width: 24.0,
+            look_ahead_time: 0.25,
+            max_look_ahead: 48.0,
+            vertical_offset: 50.0,
+            zoom: 0.4,
+            framing_margin: Vec2::new(200.0, 150.0),
+            focus: None,
+            look_ahead: 0.0,
+            zone_blend_speed: 3.0,
+            blended_zoom: 0.4,
+            blended_offset: Vec2::ZERO,
+            axis_lock_weights: Vec2::ZERO,
+            axis_lock_center: Vec2::ZERO,
+            shake_offset: Vec2::ZERO,
+        }
+    }
+}
+
+/// Lets players turn off camera effects that can cause discomfort.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    pub screen_shake: bool,
+    pub hit_stop: bool,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        AccessibilitySettings {
+            screen_shake: true,
+            hit_stop: true,
+        }
+    }
+}
+
+/// Trauma based screen shake, the camera shakes with the square of the trauma.
+#[derive(Resource, Clone, Debug)]
+pub struct ScreenShake {
+    /// From `0.0` to `1.0`, decays over time
+    pub trauma: f32,
+    /// Trauma lost per second
+    pub decay: f32,
+    /// How far the camera moves at full trauma, in world units
+    pub max_offset: f32,
+}
+
+impl Default for ScreenShake {
+    fn default() -> Self {
+        ScreenShake {
+            trauma: 0.0,
+            decay: 1.5,
+            max_offset: 12.0,
+        }
+    }
+}
+
+impl ScreenShake {
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).clamp(0.0, 1.0);
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.trauma * self.trauma
+    }
+}
+
+/// Adds trauma to the [`ScreenShake`].
+#[derive(Event)]
+pub struct ShakeEvent {
+    pub trauma: f32,
+}
+
+/// Freezes virtual time for a moment to emphasize an impact.
+#[derive(Event)]
+pub struct HitStopEvent {
+    pub duration: Duration,
+}
+
+impl HitStopEvent {
+    pub fn from_millis(millis: u64) -> Self {
+        HitStopEvent { duration: Duration::from_millis(millis) }
+    }
+}
+
+/// The running hit-stop, counted down in real time since virtual time is paused.
+#[derive(Resource, Default)]
+pub struct HitStop {
+    pub timer: Option<Timer>,
+}
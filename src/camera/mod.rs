@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy_xpbd_2d::prelude::PhysicsSet;
+use crate::camera::components::{AccessibilitySettings, HitStop, HitStopEvent, ScreenShake, ShakeEvent};
+use crate::camera::systems::*;
+
+pub mod components;
+mod systems;
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<ShakeEvent>()
+            .add_event::<HitStopEvent>()
+            .init_resource::<AccessibilitySettings>()
+            .init_resource::<ScreenShake>()
+            .init_resource::<HitStop>()
+            .add_systems(Update, zoom_scale_system)
+            .add_systems(PostUpdate, camera_zone_system.after(PhysicsSet::Sync))
+            .add_systems(PostUpdate, camera_follow_player_system.after(camera_zone_system).before(TransformSystem::TransformPropagate))
+            .add_systems(PostUpdate, gameplay_camera_feedback_system)
+            .add_systems(PostUpdate, screen_shake_system.after(gameplay_camera_feedback_system).after(camera_follow_player_system).before(TransformSystem::TransformPropagate))
+            .add_systems(PostUpdate, hit_stop_system.after(gameplay_camera_feedback_system))
+        ;
+    }
+}
@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_ecs_ldtk::prelude::LevelIid;
+use bevy_xpbd_2d::prelude::LinearVelocity;
+use rand::Rng;
+use crate::{MainCamera, Player};
+use crate::camera::components::{AccessibilitySettings, CameraRig, HitStop, HitStopEvent, ScreenShake, ShakeEvent};
+use crate::character_controller::components::Landed;
+use crate::enemy::components::EnemyKilled;
+use crate::player::components::PlayerHurt;
+use crate::world::components::{CameraZone, CurrentLevel, IsDead, LevelBounds};
+
+pub fn camera_follow_player_system(
+    time: Res<Time>,
+    player_query: Query<(&Transform, &LinearVelocity), (With<Player>, Without<IsDead>)>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection, &mut CameraRig), (With<MainCamera>, Without<Player>)>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    level_query: Query<(&LevelIid, &LevelBounds, &GlobalTransform)>,
+    current_level: Res<CurrentLevel>,
+) {
+    let Ok((mut camera_transform, mut projection, mut rig)) = camera_query.get_single_mut() else { return };
+    let Ok(window) = window_query.get_single() else { return };
+    let window_size = Vec2::new(window.width(), window.height());
+
+    let mut players = player_query.iter();
+    if let Some((first_player, _)) = players.next() {
+        let bounds = players.fold(Rect::from_center_size(first_player.translation.truncate(), Vec2::ZERO), |bounds, (player, _)| {
+            bounds.union_point(player.translation.truncate())
+        });
+        camera_transform.translation.z = first_player.translation.z;
+
+        // Only follow horizontally once the players leave the dead-zone
+        let target = bounds.center();
+        let mut focus = rig.focus.unwrap_or(target);
+        let offset = target.x - focus.x;
+        if offset.abs() > rig.dead_zone_half_width {
+            focus.x = target.x - offset.signum() * rig.dead_zone_half_width;
+        }
+        focus.y = target.y;
+        rig.focus = Some(focus);
+
+        let player_count = player_query.iter().len() as f32;
+        let average_velocity = player_query.iter().map(|(_, velocity)| velocity.x).sum::<f32>() / player_count;
+        let desired_look_ahead = (average_velocity * rig.look_ahead_time).clamp(-rig.max_look_ahead, rig.max_look_ahead);
+        let look_ahead_blend = 1.0 - (-rig.smoothing * 0.5 * time.delta_seconds()).exp();
+        rig.look_ahead += (desired_look_ahead - rig.look_ahead) * look_ahead_blend;
+
+        // Zoom out when the players move apart so everyone stays on screen
+        let required_scale = (bounds.size() + rig.framing_margin) / window_size;
+        projection.scale = rig.blended_zoom.max(required_scale.max_element());
+    }
+    // Without living players the camera rests where they were last seen
+    let Some(focus) = rig.focus else { return };
+
+    let mut desired = focus + Vec2::new(rig.look_ahead, rig.vertical_offset);
+    // Locked axes are pinned to the center of the camera zone
+    desired += (rig.axis_lock_center - desired) * rig.axis_lock_weights;
+    desired += rig.blended_offset;
+
+    // Never show what lies beyond the edges of the current level
+    let level_rect = level_query.iter()
+        .find(|(level_iid, _, _)| current_level.0.as_ref() == Some(&level_iid.to_string()))
+        .map(|(_, bounds, transform)| bounds.rect(transform));
+    if let Some(level_rect) = level_rect {
+        let half_view = window_size * projection.scale / 2.0;
+        desired.x = clamp_to_level(desired.x, level_rect.min.x, level_rect.max.x, half_view.x);
+        desired.y = clamp_to_level(desired.y, level_rect.min.y, level_rect.max.y, half_view.y);
+    }
+
+    let blend = 1.0 - (-rig.smoothing * time.delta_seconds()).exp();
+    // Smooth the unshaken position so the shake doesn't feed back into the follow
+    let current = camera_transform.translation.truncate() - rig.shake_offset;
+    let position = current.lerp(desired, blend) + rig.shake_offset;
+    camera_transform.translation = position.extend(camera_transform.translation.z);
+}
+
+/// Keeps the view inside `min..max`, centering it when the level is smaller than the view.
+fn clamp_to_level(value: f32, min: f32, max: f32, half_view: f32) -> f32 {
+    if max - min <= half_view * 2.0 {
+        (min + max) / 2.0
+    } else {
+        value.clamp(min + half_view, max - half_view)
+    }
+}
+
+/// Blends the camera rig towards the settings of the camera zone a player is in.
+pub fn camera_zone_system(
+    time: Res<Time>,
+    zone_query: Query<(&CameraZone, &GlobalTransform)>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+    mut camera_query: Query<&mut CameraRig, With<MainCamera>>,
+) {
+    let Ok(mut rig) = camera_query.get_single_mut() else { return };
+
+    let active_zone = zone_query.iter().find(|(zone, transform)| {
+        let zone_rect = Rect::from_center_size(transform.translation().truncate(), zone.size);
+        player_query.iter().any(|player_transform| zone_rect.contains(player_transform.translation().truncate()))
+    });
+
+    let (target_zoom, target_offset, target_lock_weights) = match active_zone {
+        Some((zone, transform)) => {
+            // Leaving a zone fades out its lock towards the same center
+            rig.axis_lock_center = transform.translation().truncate();
+            (zone.zoom.unwrap_or(rig.zoom), zone.offset, zone.lock_axis.weights())
+        }
+        None => (rig.zoom, Vec2::ZERO, Vec2::ZERO),
+    };
+
+    let blend = 1.0 - (-rig.zone_blend_speed * time.delta_seconds()).exp();
+    rig.blended_zoom += (target_zoom - rig.blended_zoom) * blend;
+    rig.blended_offset = rig.blended_offset.lerp(target_offset, blend);
+    rig.axis_lock_weights = rig.axis_lock_weights.lerp(target_lock_weights, blend);
+}
+
+pub fn zoom_scale_system(
+    mut query_camera: Query<(&mut OrthographicProjection, &mut CameraRig), Added<MainCamera>>,
+) {
+    //if we scale the projection at creation, playersprite won't be rendered
+    for (mut projection, mut rig) in query_camera.iter_mut() {
+        projection.scale = rig.zoom;
+        rig.blended_zoom = rig.zoom;
+    }
+}
+
+/// Falling faster than this shakes the camera on landing.
+const HEAVY_LANDING_SPEED: f32 = 550.0;
+
+/// Shakes the camera and stops time briefly on impactful gameplay events.
+pub fn gameplay_camera_feedback_system(
+    mut player_hurt_events: EventReader<PlayerHurt>,
+    mut enemy_killed_events: EventReader<EnemyKilled>,
+    mut landed_events: EventReader<Landed>,
+    player_query: Query<(), With<Player>>,
+    mut shake_event: EventWriter<ShakeEvent>,
+    mut hit_stop_event: EventWriter<HitStopEvent>,
+) {
+    for _ in player_hurt_events.read() {
+        shake_event.send(ShakeEvent { trauma: 0.6 });
+        hit_stop_event.send(HitStopEvent::from_millis(120));
+    }
+    for _ in enemy_killed_events.read() {
+        shake_event.send(ShakeEvent { trauma: 0.3 });
+        hit_stop_event.send(HitStopEvent::from_millis(60));
+    }
+    for landed_event in landed_events.read() {
+        if !player_query.contains(landed_event.entity) || landed_event.impact_speed < HEAVY_LANDING_SPEED { continue; }
+        let trauma = ((landed_event.impact_speed - HEAVY_LANDING_SPEED) / 1000.0 + 0.2).min(0.5);
+        shake_event.send(ShakeEvent { trauma });
+    }
+}
+
+pub fn screen_shake_system(
+    real_time: Res<Time<Real>>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    mut screen_shake: ResMut<ScreenShake>,
+    mut shake_events: EventReader<ShakeEvent>,
+    mut camera_query: Query<(&mut Transform, &mut CameraRig), With<MainCamera>>,
+) {
+    for shake_event in shake_events.read() {
+        if accessibility_settings.screen_shake {
+            screen_shake.add_trauma(shake_event.trauma);
+        }
+    }
+    if !accessibility_settings.screen_shake {
+        screen_shake.trauma = 0.0;
+    }
+
+    let Ok((mut camera_transform, mut rig)) = camera_query.get_single_mut() else { return };
+
+    // Real time, so the camera keeps shaking during hit-stop and slow motion
+    let decay = screen_shake.decay * real_time.delta_seconds();
+    screen_shake.trauma = (screen_shake.trauma - decay).max(0.0);
+
+    let mut rng = rand::thread_rng();
+    let direction = Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0));
+    let shake_offset = direction * screen_shake.max_offset * screen_shake.intensity();
+
+    let unshaken = camera_transform.translation.truncate() - rig.shake_offset;
+    rig.shake_offset = shake_offset;
+    camera_transform.translation = (unshaken + shake_offset).extend(camera_transform.translation.z);
+}
+
+/// Pauses virtual time while a hit-stop is running.
+pub fn hit_stop_system(
+    real_time: Res<Time<Real>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    accessibility_settings: Res<AccessibilitySettings>,
+    mut hit_stop: ResMut<HitStop>,
+    mut hit_stop_events: EventReader<HitStopEvent>,
+) {
+    for hit_stop_event in hit_stop_events.read() {
+        if !accessibility_settings.hit_stop { continue; }
+        // Overlapping hit-stops don't add up, the longest one wins
+        let remaining = hit_stop.timer.as_ref().map_or(default(), |timer| timer.remaining());
+        if hit_stop_event.duration > remaining {
+            hit_stop.timer = Some(Timer::new(hit_stop_event.duration, TimerMode::Once));
+        }
+    }
+
+    let Some(timer) = hit_stop.timer.as_mut() else { return };
+    timer.tick(real_time.delta());
+    if timer.finished() || !accessibility_settings.hit_stop {
+        hit_stop.timer = None;
+        virtual_time.unpause();
+    } else if !virtual_time.is_paused() {
+        virtual_time.pause();
+    }
+}
@@ -7,11 +7,43 @@ use bevy_xpbd_2d::prelude::*;
 #[derive(Component)]
 pub struct CharacterController;
 
+/// The movement a character controller should perform this frame.
+///
+/// Whatever drives the character (player input, AI, replays) writes this every frame,
+/// and the character controller systems consume it.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct MovementIntent {
+    /// Horizontal movement in the range `-1.0..=1.0`.
+    pub direction: Scalar,
+    /// Jump started being pressed this frame.
+    pub jump_just_pressed: bool,
+    /// Jump was released this frame.
+    pub jump_just_released: bool,
+    /// Drop down through one-way platforms.
+    pub drop_down: bool,
+}
+
 /// A marker component indicating that an entity is on the ground.
 #[derive(Component)]
 #[component(storage = "SparseSet")]
 pub struct Grounded;
 
+/// The body a character is standing on, used to carry it along with moving platforms.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct GroundedOn {
+    /// The rigid body entity of the ground.
+    pub entity: Entity,
+    /// The velocity of the ground, zero unless it is a kinematic body.
+    pub velocity: Vector,
+    /// The distance between the character and the ground.
+    pub distance: Scalar,
+}
+
+/// The horizontal ground velocity a character was last carried along with.
+#[derive(Component, Default)]
+pub struct CarriedGroundVelocity(pub Scalar);
+
 /// The acceleration used for character movement.
 #[derive(Component)]
 pub struct MovementAcceleration(pub Scalar);
@@ -30,17 +62,88 @@ pub struct AirDampingFactor(pub Scalar);
 #[derive(Component)]
 pub struct JumpImpulse(pub Scalar);
 
+/// How long (in seconds) after walking off a ledge a jump is still allowed.
+#[derive(Component)]
+pub struct CoyoteTime(pub Scalar);
+
+/// How long (in seconds) a jump input is remembered before the character lands.
+#[derive(Component)]
+pub struct JumpBufferTime(pub Scalar);
+
+/// Multiplier applied to upward velocity when jump is released while still rising,
+/// allowing short hops by tapping jump.
+#[derive(Component)]
+pub struct JumpCutFactor(pub Scalar);
+
+/// Multiplier applied on top of the regular gravity while the character is falling.
+#[derive(Component)]
+pub struct FallGravityMultiplier(pub Scalar);
+
+/// Timestamps used to resolve [`CoyoteTime`] and [`JumpBufferTime`].
+#[derive(Component, Default)]
+pub struct JumpTimers {
+    /// Elapsed time at which the character was last [`Grounded`].
+    pub last_grounded: Option<Scalar>,
+    /// Elapsed time at which jump was last pressed and not yet consumed.
+    pub last_jump_pressed: Option<Scalar>,
+}
+
 /// The maximum angle a slope can have for a character controller
 /// to be able to climb and jump. If the slope is steeper than this angle,
 /// the character will slide down.
 #[derive(Component)]
 pub struct MaxSlopeAngle(pub Scalar);
 
+/// Detects walls next to the character by casting its collider
+/// to the left and right over the given distance.
+#[derive(Component)]
+pub struct WallDetection(pub Scalar);
+
+/// A component indicating that a character touches a wall,
+/// `direction` is `-1.0` for a wall on the left and `1.0` for a wall on the right.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct TouchingWall {
+    pub direction: Scalar,
+}
+
+/// The maximum fall speed while sliding down a wall.
+#[derive(Component)]
+pub struct WallSlideSpeed(pub Scalar);
+
+/// A marker component indicating that an entity is sliding down a wall.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct WallSliding;
+
+/// The velocity of a jump off a wall, `x` points away from the wall.
+#[derive(Component)]
+pub struct WallJumpImpulse(pub Vector);
+
+/// A bundle that enables wall sliding and wall jumping for a character controller.
+#[derive(Bundle)]
+pub struct WallMovementBundle {
+    wall_detection: WallDetection,
+    wall_slide_speed: WallSlideSpeed,
+    wall_jump_impulse: WallJumpImpulse,
+}
+
+impl WallMovementBundle {
+    pub const fn new(detection_distance: Scalar, slide_speed: Scalar, jump_impulse: Vector) -> Self {
+        Self {
+            wall_detection: WallDetection(detection_distance),
+            wall_slide_speed: WallSlideSpeed(slide_speed),
+            wall_jump_impulse: WallJumpImpulse(jump_impulse),
+        }
+    }
+}
+
 /// A bundle that contains the components needed for a basic
 /// kinematic character controller.
 #[derive(Bundle)]
 pub struct CharacterControllerBundle {
     character_controller: CharacterController,
+    intent: MovementIntent,
     rigid_body: RigidBody,
     collider: Collider,
     ground_caster: ShapeCaster,
@@ -56,7 +159,11 @@ pub struct MovementBundle {
     damping: MovementDampingFactor,
     air_damping: AirDampingFactor,
     jump_impulse: JumpImpulse,
+    coyote_time: CoyoteTime,
+    jump_buffer_time: JumpBufferTime,
+    jump_timers: JumpTimers,
     max_slope_angle: MaxSlopeAngle,
+    carried_ground_velocity: CarriedGroundVelocity,
 }
 
 impl MovementBundle {
@@ -74,9 +181,22 @@ impl MovementBundle {
             damping: MovementDampingFactor(damping),
             air_damping: AirDampingFactor(air_damping),
             jump_impulse: JumpImpulse(jump_impulse),
+            coyote_time: CoyoteTime(0.0),
+            jump_buffer_time: JumpBufferTime(0.0),
+            jump_timers: JumpTimers {
+                last_grounded: None,
+                last_jump_pressed: None,
+            },
             max_slope_angle: MaxSlopeAngle(max_slope_angle),
+            carried_ground_velocity: CarriedGroundVelocity(0.0),
         }
     }
+
+    pub fn with_jump_assist(mut self, coyote_time: Scalar, jump_buffer_time: Scalar) -> Self {
+        self.coyote_time = CoyoteTime(coyote_time);
+        self.jump_buffer_time = JumpBufferTime(jump_buffer_time);
+        self
+    }
 }
 
 impl Default for MovementBundle {
@@ -93,6 +213,7 @@ impl CharacterControllerBundle {
 
         Self {
             character_controller: CharacterController,
+            intent: MovementIntent::default(),
             rigid_body: RigidBody::Dynamic,
             collider,
             ground_caster: ShapeCaster::new(caster_shape, Vector::ZERO, 0.0, Direction2d::NEG_Y)
@@ -114,4 +235,24 @@ impl CharacterControllerBundle {
         self.movement = MovementBundle::new(acceleration, air_acceleration, damping, air_damping, jump_impulse, max_slope_angle);
         self
     }
-}
\ No newline at end of file
+
+    /// Sets the coyote time and jump buffer windows, both in seconds.
+    pub fn with_jump_assist(mut self, coyote_time: Scalar, jump_buffer_time: Scalar) -> Self {
+        self.movement = self.movement.with_jump_assist(coyote_time, jump_buffer_time);
+        self
+    }
+}
+
+/// Sent when a character controller jumps off the ground or a wall.
+#[derive(Event)]
+pub struct JumpStarted {
+    pub entity: Entity,
+}
+
+/// Sent when a character controller touches the ground after being in the air.
+#[derive(Event)]
+pub struct Landed {
+    pub entity: Entity,
+    /// How fast the character was falling when it touched the ground
+    pub impact_speed: Scalar,
+}
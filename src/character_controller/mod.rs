@@ -2,22 +2,38 @@ pub mod components;
 mod systems;
 
 use bevy::{prelude::*};
+use crate::character_controller::components::{JumpStarted, Landed};
 use crate::character_controller::systems::*;
+use crate::game_state::components::GameState;
 
 pub struct CharacterControllerPlugin;
 
+/// The systems that consume [`MovementIntent`](components::MovementIntent),
+/// anything writing intents should run before this set.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct CharacterControllerSet;
+
 impl Plugin for CharacterControllerPlugin {
     fn build(&self, app: &mut App) {
         app
+            .add_event::<JumpStarted>()
+            .add_event::<Landed>()
+            .configure_sets(Update, CharacterControllerSet.run_if(in_state(GameState::Playing)))
             .add_systems(
                 Update,
                 (
                     update_grounded_system,
+                    update_wall_contact_system,
                     movement_system,
+                    wall_jump_system,
+                    follow_moving_ground_system,
+                    apply_fall_gravity_system,
+                    wall_slide_system,
                     apply_movement_damping_system,
                     activate_pass_through_one_way_platform_system,
                 )
-                    .chain(),
+                    .chain()
+                    .in_set(CharacterControllerSet),
             );
     }
 }
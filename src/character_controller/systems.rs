@@ -1,27 +1,31 @@
 use bevy::prelude::*;
 use bevy_xpbd_2d::components::{Rotation, Sleeping};
 use bevy_xpbd_2d::math::{AdjustPrecision, Vector};
-use bevy_xpbd_2d::prelude::{LinearVelocity, ShapeHits};
-use leafwing_input_manager::action_state::ActionState;
-use crate::character_controller::components::{AirAcceleration, AirDampingFactor, CharacterController, Grounded, JumpImpulse, MaxSlopeAngle, MovementAcceleration, MovementDampingFactor};
-use crate::Player;
-use crate::player::components::PlayerAction;
-use crate::world::components::{IsDead, PassThroughOneWayPlatform};
+use bevy_xpbd_2d::prelude::{Collider, ColliderParent, Gravity, GravityScale, LinearVelocity, Position, RigidBody, ShapeHits, SpatialQuery, SpatialQueryFilter};
+use crate::character_controller::components::{AirAcceleration, AirDampingFactor, CarriedGroundVelocity, CharacterController, CoyoteTime, FallGravityMultiplier, Grounded, GroundedOn, JumpBufferTime, JumpCutFactor, JumpImpulse, JumpStarted, JumpTimers, Landed, MaxSlopeAngle, MovementAcceleration, MovementDampingFactor, MovementIntent, TouchingWall, WallDetection, WallJumpImpulse, WallSlideSpeed, WallSliding};
+use crate::world::components::{GamePhysicsLayer, IsDead, OneWayPlatform, PassThroughOneWayPlatform};
 
 
 
-/// Updates the [`Grounded`] status for character controllers.
+/// Updates the [`Grounded`] and [`GroundedOn`] status for character controllers
+/// and remembers when they were last on the ground for [`CoyoteTime`].
 pub fn update_grounded_system(
     mut commands: Commands,
+    time: Res<Time>,
     mut query: Query<
-        (Entity, &ShapeHits, &Rotation, Option<&MaxSlopeAngle>),
+        (Entity, &ShapeHits, &Rotation, &LinearVelocity, Option<&MaxSlopeAngle>, Option<&mut JumpTimers>, Has<Grounded>),
         With<CharacterController>,
     >,
+    collider_parent: Query<&ColliderParent>,
+    bodies: Query<(&RigidBody, &LinearVelocity)>,
+    mut landed_event: EventWriter<Landed>,
 ) {
-    for (entity, hits, rotation, max_slope_angle) in &mut query {
+    let now = time.elapsed_seconds_f64().adjust_precision();
+
+    for (entity, hits, rotation, linear_velocity, max_slope_angle, jump_timers, was_grounded) in &mut query {
         // The character is grounded if the shape caster has a hit with a normal
         // that isn't too steep.
-        let is_grounded = hits.iter().any(|hit| {
+        let ground_hit = hits.iter().find(|hit| {
             if let Some(angle) = max_slope_angle {
                 rotation.rotate(-hit.normal2).angle_between(Vector::Y).abs() <= angle.0
             } else {
@@ -29,61 +33,246 @@ pub fn update_grounded_system(
             }
         });
 
-        if is_grounded {
-            commands.entity(entity).insert(Grounded);
+        if let Some(hit) = ground_hit {
+            // Colliders are often children of the body that moves them
+            let body = collider_parent.get(hit.entity).map_or(hit.entity, |parent| parent.get());
+            let velocity = match bodies.get(body) {
+                Ok((RigidBody::Kinematic, linear_velocity)) => linear_velocity.0,
+                _ => Vector::ZERO,
+            };
+
+            commands.entity(entity).insert((
+                Grounded,
+                GroundedOn {
+                    entity: body,
+                    velocity,
+                    distance: hit.time_of_impact,
+                },
+            ));
+            // Freshly spawned characters have never been on the ground, they start on it instead of landing
+            let has_been_grounded = jump_timers.as_ref().is_some_and(|jump_timers| jump_timers.last_grounded.is_some());
+            if let Some(mut jump_timers) = jump_timers {
+                jump_timers.last_grounded = Some(now);
+            }
+            if !was_grounded && has_been_grounded {
+                // The ground caster reaches below the character, so it still moves at its falling speed
+                landed_event.send(Landed { entity, impact_speed: (-linear_velocity.y).max(0.0) });
+            }
         } else {
-            commands.entity(entity).remove::<Grounded>();
+            commands.entity(entity).remove::<(Grounded, GroundedOn)>();
         }
     }
 }
 
-/// Responds to [`MovementAction`] events and moves character controllers accordingly.
+/// Updates the [`TouchingWall`] status for character controllers with [`WallDetection`].
+pub fn update_wall_contact_system(
+    mut commands: Commands,
+    spatial_query: SpatialQuery,
+    query: Query<(Entity, &Collider, &Position, &Rotation, &WallDetection), With<CharacterController>>,
+    collider_parent: Query<&ColliderParent>,
+    one_way_platforms: Query<(), With<OneWayPlatform>>,
+) {
+    for (entity, collider, position, rotation, wall_detection) in query.iter() {
+        let filter = SpatialQueryFilter::from_mask(GamePhysicsLayer::Ground)
+            .with_excluded_entities([entity]);
+
+        let wall_direction = [Direction2d::NEG_X, Direction2d::X].into_iter().find(|direction| {
+            spatial_query.shape_hits(
+                collider,
+                position.0,
+                rotation.as_radians(),
+                *direction,
+                wall_detection.0,
+                4,
+                true,
+                filter.clone(),
+            ).iter().any(|hit| {
+                // One-way platforms can be passed from the side, so they don't count as walls
+                let body = collider_parent.get(hit.entity).map_or(hit.entity, |parent| parent.get());
+                if one_way_platforms.contains(body) { return false; }
+                // Ignore the floor and ceiling we are sliding along
+                rotation.rotate(-hit.normal2).dot(**direction).abs() > 0.7
+            })
+        });
+
+        if let Some(direction) = wall_direction {
+            commands.entity(entity).insert(TouchingWall { direction: direction.x });
+        } else {
+            commands.entity(entity).remove::<TouchingWall>();
+        }
+    }
+}
+
+/// Responds to each controller's [`MovementIntent`] and moves it accordingly.
 pub fn movement_system(
     time: Res<Time>,
-    player_actions_query: Query<&ActionState<PlayerAction>>,
     mut controllers: Query<(
+        Entity,
+        &MovementIntent,
         &MovementAcceleration,
         &AirAcceleration,
         &JumpImpulse,
+        &CoyoteTime,
+        &JumpBufferTime,
+        &mut JumpTimers,
         &mut LinearVelocity,
+        Option<&JumpCutFactor>,
         Has<Grounded>,
     ), Without<IsDead>>,
+    mut jump_started_event: EventWriter<JumpStarted>,
 ) {
     // Precision is adjusted so that the example works with
     // both the `f32` and `f64` features. Otherwise you don't need this.
     let delta_time = time.delta_seconds_f64().adjust_precision();
+    let now = time.elapsed_seconds_f64().adjust_precision();
 
-    let Ok(input) = player_actions_query.get_single() else { return };
-    // for event in movement_event_reader.read() {
-    for (movement_acceleration, air_acceleration, jump_impulse, mut linear_velocity, is_grounded) in
-        &mut controllers
+    for (
+        entity,
+        intent,
+        movement_acceleration,
+        air_acceleration,
+        jump_impulse,
+        coyote_time,
+        jump_buffer_time,
+        mut jump_timers,
+        mut linear_velocity,
+        jump_cut_factor,
+        is_grounded,
+    ) in &mut controllers
     {
-        if input.pressed(&PlayerAction::Move) {
-            let direction = input.clamped_axis_pair(&PlayerAction::Move).unwrap().x();
-            if is_grounded {
-                linear_velocity.x += direction * movement_acceleration.0 * delta_time;
-            } else {
-                linear_velocity.x += direction * air_acceleration.0 * delta_time;
-            }
+        if is_grounded {
+            linear_velocity.x += intent.direction * movement_acceleration.0 * delta_time;
+        } else {
+            linear_velocity.x += intent.direction * air_acceleration.0 * delta_time;
+        }
+
+        if intent.jump_just_pressed {
+            jump_timers.last_jump_pressed = Some(now);
         }
 
-        if input.just_pressed(&PlayerAction::Jump) {
-            if is_grounded {
-                linear_velocity.y = jump_impulse.0;
+        // A jump pressed shortly before landing is buffered,
+        // and one pressed shortly after leaving a ledge still counts.
+        let jump_requested = jump_timers.last_jump_pressed
+            .is_some_and(|pressed_at| now - pressed_at <= jump_buffer_time.0);
+        let can_jump = is_grounded || jump_timers.last_grounded
+            .is_some_and(|grounded_at| now - grounded_at <= coyote_time.0);
+
+        if jump_requested && can_jump {
+            linear_velocity.y = jump_impulse.0;
+            jump_timers.last_jump_pressed = None;
+            jump_timers.last_grounded = None;
+            jump_started_event.send(JumpStarted { entity });
+        }
+
+        if let Some(jump_cut_factor) = jump_cut_factor {
+            if intent.jump_just_released && linear_velocity.y > 0.0 {
+                linear_velocity.y *= jump_cut_factor.0;
             }
         }
     }
 }
 
-/// Slows down movement in the X direction.
+/// Jumps away from walls when jump was pressed while touching a wall in the air.
+pub fn wall_jump_system(
+    time: Res<Time>,
+    mut query: Query<(
+        Entity,
+        &WallJumpImpulse,
+        &JumpBufferTime,
+        &mut JumpTimers,
+        &TouchingWall,
+        &mut LinearVelocity,
+    ), (Without<Grounded>, Without<IsDead>)>,
+    mut jump_started_event: EventWriter<JumpStarted>,
+) {
+    let now = time.elapsed_seconds_f64().adjust_precision();
+
+    for (entity, wall_jump_impulse, jump_buffer_time, mut jump_timers, touching_wall, mut linear_velocity) in &mut query {
+        let jump_requested = jump_timers.last_jump_pressed
+            .is_some_and(|pressed_at| now - pressed_at <= jump_buffer_time.0);
+        if !jump_requested { continue; }
+
+        linear_velocity.0 = Vector::new(
+            -touching_wall.direction * wall_jump_impulse.0.x,
+            wall_jump_impulse.0.y,
+        );
+        jump_timers.last_jump_pressed = None;
+        jump_started_event.send(JumpStarted { entity });
+    }
+}
+
+/// Caps the fall speed of characters pushing against a wall in the air.
+pub fn wall_slide_system(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &WallSlideSpeed,
+        &MovementIntent,
+        &mut LinearVelocity,
+        Option<&TouchingWall>,
+        Has<Grounded>,
+    ), Without<IsDead>>,
+) {
+    for (entity, wall_slide_speed, intent, mut linear_velocity, touching_wall, is_grounded) in &mut query {
+        let is_sliding = !is_grounded
+            && linear_velocity.y < 0.0
+            && touching_wall.is_some_and(|wall| intent.direction * wall.direction > 0.0);
+
+        if is_sliding {
+            linear_velocity.y = linear_velocity.y.max(-wall_slide_speed.0);
+            commands.entity(entity).insert(WallSliding);
+        } else {
+            commands.entity(entity).remove::<WallSliding>();
+        }
+    }
+}
+
+/// Keeps characters standing on moving ground attached to it vertically,
+/// horizontal movement is carried along by [`apply_movement_damping_system`].
+pub fn follow_moving_ground_system(
+    mut query: Query<(&GroundedOn, &mut LinearVelocity), Without<IsDead>>,
+) {
+    for (grounded_on, mut linear_velocity) in &mut query {
+        let ground_velocity = grounded_on.velocity;
+        if ground_velocity.y == 0.0 || grounded_on.distance > 1.0 { continue; }
+        // Don't hold back characters that are jumping off the ground
+        if linear_velocity.y > ground_velocity.y.max(0.0) + 1.0 { continue; }
+        linear_velocity.y = ground_velocity.y;
+    }
+}
+
+/// Applies the additional gravity of [`FallGravityMultiplier`] to falling characters.
+pub fn apply_fall_gravity_system(
+    time: Res<Time>,
+    gravity: Res<Gravity>,
+    mut query: Query<(&FallGravityMultiplier, Option<&GravityScale>, &mut LinearVelocity), Without<Grounded>>,
+) {
+    let delta_time = time.delta_seconds_f64().adjust_precision();
+
+    for (fall_gravity_multiplier, gravity_scale, mut linear_velocity) in &mut query {
+        if linear_velocity.y >= 0.0 { continue; }
+        let gravity_scale = gravity_scale.map_or(1.0, |scale| scale.0);
+        // The physics engine already applies gravity once, only add the difference
+        linear_velocity.0 += gravity.0 * gravity_scale * (fall_gravity_multiplier.0 - 1.0) * delta_time;
+    }
+}
+
+/// Carries characters along with the ground they stand on and slows down their own movement in the X direction.
 pub fn apply_movement_damping_system(
-    mut query: Query<(&MovementDampingFactor, &AirDampingFactor, &mut LinearVelocity, Has<Grounded>)>
+    mut query: Query<(&MovementDampingFactor, &AirDampingFactor, &mut CarriedGroundVelocity, &mut LinearVelocity, Option<&GroundedOn>)>
 ) {
-    for (damping_factor, air_damping_factor, mut linear_velocity, is_grounded) in &mut query {
+    for (damping_factor, air_damping_factor, mut carried_ground_velocity, mut linear_velocity, grounded_on) in &mut query {
         // We could use `LinearDamping`, but we don't want to dampen movement along the Y axis
-        if is_grounded {
-            linear_velocity.x *= damping_factor.0;
+        if let Some(grounded_on) = grounded_on {
+            // Follow every change of the ground velocity right away, so reversing platforms don't make characters slip
+            let ground_velocity = grounded_on.velocity.x;
+            linear_velocity.x += ground_velocity - carried_ground_velocity.0;
+            carried_ground_velocity.0 = ground_velocity;
+            // Only damp the movement relative to the ground
+            linear_velocity.x = ground_velocity + (linear_velocity.x - ground_velocity) * damping_factor.0;
         } else {
+            // Characters leaving the ground keep its momentum
+            carried_ground_velocity.0 = 0.0;
             linear_velocity.x *= air_damping_factor.0;
         }
     }
@@ -92,13 +281,10 @@ pub fn apply_movement_damping_system(
 
 pub fn activate_pass_through_one_way_platform_system(
     mut commands: Commands,
-    player_actions_query: Query<&ActionState<PlayerAction>>,
-    mut players: Query<(Entity, &mut PassThroughOneWayPlatform), With<Player>>,
+    mut controllers: Query<(Entity, &MovementIntent, &mut PassThroughOneWayPlatform), With<CharacterController>>,
 ) {
-    let Ok(input) = player_actions_query.get_single() else { return };
-
-    for (entity, mut pass_through_one_way_platform) in &mut players {
-        if input.just_pressed(&PlayerAction::DropDown) {
+    for (entity, intent, mut pass_through_one_way_platform) in &mut controllers {
+        if intent.drop_down {
             *pass_through_one_way_platform = PassThroughOneWayPlatform::Always;
             // Wake up body when it's allowed to drop down.
             // Otherwise it won't fall because gravity isn't simulated.
@@ -11,16 +11,69 @@ pub struct Enemy;
 #[derive(Copy, Clone, PartialEq, Debug, Component)]
 pub struct MovementDirection(pub Direction2d);
 
+/// The hit points of an enemy, it dies when they reach zero.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Component)]
+pub struct EnemyHealth(pub u32);
+
+/// An enemy that was killed and plays its death animation before it is despawned.
+#[derive(Clone, Debug, Component)]
+pub struct EnemyDying(pub Timer);
+
+/// Lets an entity kill enemies by landing on top of them, bouncing it back up.
+#[derive(Copy, Clone, PartialEq, Debug, Component)]
+pub struct Stomper {
+    pub bounce_impulse: f32,
+}
+
+#[derive(Event)]
+pub struct EnemyHit {
+    pub enemy: Entity,
+    pub damage: u32,
+}
+
+#[derive(Event)]
+pub struct EnemyKilled {
+    pub enemy: Entity,
+    /// Where the enemy was in the world
+    pub translation: Vec3,
+}
+
+
+/// What an enemy does when it reaches the end of the ground it walks on.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub enum EdgeBehavior {
+    /// Turns around and stays on its platform
+    #[default]
+    Patrol,
+    /// Keeps walking and falls off
+    WalksOffEdges,
+}
+
+impl EdgeBehavior {
+    fn from_field(entity_instance: &EntityInstance) -> Self {
+        match entity_instance.get_bool_field("walks_off_edges") {
+            Ok(true) => EdgeBehavior::WalksOffEdges,
+            _ => EdgeBehavior::Patrol,
+        }
+    }
+}
+
+/// A downward ray in front of a patrolling enemy, checking for ground ahead.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct LedgeDetector;
 
 #[derive(Clone, Default, Bundle, LdtkEntity)]
 pub struct EnemyBundle {
     enemy: Enemy,
+    #[with(EdgeBehavior::from_field)]
+    edge_behavior: EdgeBehavior,
 }
 
 
 #[derive(Resource)]
 pub struct SlimeAnimations {
     pub idle: AnimationId,
+    pub squash: AnimationId,
     pub texture: Handle<Image>,
     pub layout: Handle<TextureAtlasLayout>,
 }
@@ -50,8 +103,20 @@ impl FromWorld for SlimeAnimations {
                 .add_stage(idle_clip_id.into())
                 .set_repeat(AnimationRepeat::Loop);
         });
+        let squash_clip_id = library.new_clip(|clip| {
+            clip.push_frame_indices(sprite_sheet.horizontal_strip(0, 2, 4))
+                .set_default_duration(AnimationDuration::PerFrame(100));
+        });
+
+        let squash_animation_id = library.new_animation(|animation| {
+            animation
+                .add_stage(squash_clip_id.into())
+                .set_repeat(AnimationRepeat::Cycles(1));
+        });
+
         SlimeAnimations {
             idle: idle_animation_id,
+            squash: squash_animation_id,
             texture,
             layout: texture_atlas_layout,
         }
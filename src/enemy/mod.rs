@@ -2,8 +2,10 @@ use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use crate::enemy::components::*;
 use crate::enemy::systems::*;
+use crate::game_state::components::GameState;
+use crate::world::systems::kill_zone_system;
 
-mod components;
+pub mod components;
 mod systems;
 
 pub struct EnemyPlugin;
@@ -12,9 +14,14 @@ impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app
             .init_resource::<SlimeAnimations>()
+            .add_event::<EnemyHit>()
+            .add_event::<EnemyKilled>()
             .add_systems(Update, setup_enemy_system)
-            .add_systems(Update, enemy_wall_detection_system)
-            .add_systems(Update, enemy_movement_system)
+            .add_systems(Update, enemy_wall_detection_system.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, enemy_ledge_detection_system.after(enemy_wall_detection_system).run_if(in_state(GameState::Playing)))
+            .add_systems(Update, enemy_movement_system.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, enemy_dying_system.run_if(in_state(GameState::Playing)))
+            .add_systems(PostUpdate, (enemy_stomp_system, enemy_damage_system).chain().before(kill_zone_system).run_if(in_state(GameState::Playing)))
             .register_ldtk_entity::<EnemyBundle>("Slime")
 
         ;
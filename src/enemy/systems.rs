@@ -1,15 +1,15 @@
 use bevy::prelude::*;
 use bevy_spritesheet_animation::prelude::*;
 use bevy_xpbd_2d::prelude::*;
-use crate::enemy::components::{Enemy, MovementDirection, SlimeAnimations};
-use crate::world::components::{GamePhysicsLayer, KillZone};
+use crate::enemy::components::{EdgeBehavior, Enemy, EnemyDying, EnemyHealth, EnemyHit, EnemyKilled, LedgeDetector, MovementDirection, SlimeAnimations, Stomper};
+use crate::world::components::{GamePhysicsLayer, IsDead, KillZone};
 
 pub fn setup_enemy_system(
     mut commands: Commands,
     slime_animations: Res<SlimeAnimations>,
-    enemy_query: Query<(Entity, &Transform), (Added<Enemy>, Without<SpritesheetAnimation>)>,
+    enemy_query: Query<(Entity, &Transform, &EdgeBehavior), (Added<Enemy>, Without<SpritesheetAnimation>)>,
 ) {
-    for (entity, transform) in enemy_query.iter() {
+    for (entity, transform, edge_behavior) in enemy_query.iter() {
         commands.entity(entity)
             .insert((
                 SpriteSheetBundle {
@@ -34,6 +34,7 @@ pub fn setup_enemy_system(
                     .with_max_hits(2)
                 ,
                 MovementDirection(Direction2d::X),
+                EnemyHealth(1),
                 KillZone,
                 CollisionLayers::new(GamePhysicsLayer::Enemy, [GamePhysicsLayer::Enemy, GamePhysicsLayer::Player, GamePhysicsLayer::Ground])
 
@@ -45,10 +46,23 @@ pub fn setup_enemy_system(
                 Friction::new(0.0).with_combine_rule(CoefficientCombine::Min),
                 KillZone,
             ));
+            if *edge_behavior == EdgeBehavior::Patrol {
+                commands.spawn((
+                    Name::new("LedgeDetector"),
+                    TransformBundle::default(),
+                    RayCaster::new(Vec2::new(LEDGE_LOOK_AHEAD, 0.0), Direction2d::NEG_Y)
+                        .with_max_time_of_impact(12.0)
+                        .with_query_filter(SpatialQueryFilter::from_mask(GamePhysicsLayer::Ground)),
+                    LedgeDetector,
+                ));
+            }
         });
     }
 }
 
+/// How far in front of the enemy the ground is checked.
+const LEDGE_LOOK_AHEAD: f32 = 6.0;
+
 pub fn enemy_wall_detection_system(
     mut enemy_query: Query<(Entity, &mut RayCaster, &RayHits, &mut Sprite, &mut MovementDirection), With<Enemy>>,
     collider_parent: Query<&ColliderParent>,
@@ -65,6 +79,24 @@ pub fn enemy_wall_detection_system(
     }
 }
 
+pub fn enemy_ledge_detection_system(
+    mut enemy_query: Query<(&mut RayCaster, &mut MovementDirection, &LinearVelocity), With<Enemy>>,
+    mut ledge_query: Query<(&Parent, &mut RayCaster, &RayHits), (With<LedgeDetector>, Without<Enemy>)>,
+) {
+    for (parent, mut ledge_raycaster, hits) in ledge_query.iter_mut() {
+        let Ok((mut raycaster, mut movement_direction, linear_velocity)) = enemy_query.get_mut(parent.get()) else { continue };
+
+        // Only turn around while walking on the ground, not while falling
+        let is_on_ground = linear_velocity.y.abs() < 1.0;
+        if is_on_ground && hits.is_empty() {
+            raycaster.direction = -raycaster.direction;
+            movement_direction.0 = -movement_direction.0;
+        }
+
+        ledge_raycaster.origin.x = movement_direction.0.x * LEDGE_LOOK_AHEAD;
+    }
+}
+
 pub fn enemy_movement_system(
     mut enemy_query: Query<(&mut LinearVelocity, &MovementDirection), With<Enemy>>,
 ) {
@@ -74,3 +106,84 @@ pub fn enemy_movement_system(
         }
     }
 }
+
+/// Hurts enemies that a [`Stomper`] lands on and bounces the stomper back up.
+pub fn enemy_stomp_system(
+    collisions: Res<Collisions>,
+    mut stomper_query: Query<(Entity, &Stomper, &mut LinearVelocity), Without<IsDead>>,
+    enemy_query: Query<(), (With<Enemy>, Without<EnemyDying>)>,
+    collider_parent: Query<&ColliderParent>,
+    mut enemy_hit_event: EventWriter<EnemyHit>,
+) {
+    for (entity, stomper, mut linear_velocity) in stomper_query.iter_mut() {
+        for contacts in collisions.collisions_with_entity(entity) {
+            // Each manifold normal points from its own entity towards the other one
+            let (other, is_first) = if contacts.entity1 == entity {
+                (contacts.entity2, true)
+            } else {
+                (contacts.entity1, false)
+            };
+            let enemy = collider_parent.get(other).map_or(other, |parent| parent.get());
+            if !enemy_query.contains(enemy) { continue; }
+
+            let hit_from_above = contacts.manifolds.iter().any(|manifold| {
+                let normal = if is_first { manifold.normal1 } else { manifold.normal2 };
+                normal.y < -0.5
+            });
+            if !hit_from_above { continue; }
+
+            linear_velocity.y = stomper.bounce_impulse;
+            enemy_hit_event.send(EnemyHit { enemy, damage: 1 });
+        }
+    }
+}
+
+pub fn enemy_damage_system(
+    mut commands: Commands,
+    mut enemy_hit_events: EventReader<EnemyHit>,
+    mut enemy_query: Query<(&mut EnemyHealth, &mut SpritesheetAnimation, &GlobalTransform, &Children), Without<EnemyDying>>,
+    slime_animations: Res<SlimeAnimations>,
+    mut enemy_killed_event: EventWriter<EnemyKilled>,
+) {
+    for hit in enemy_hit_events.read() {
+        let Ok((mut health, mut animation, transform, children)) = enemy_query.get_mut(hit.enemy) else { continue };
+        health.0 = health.0.saturating_sub(hit.damage);
+        if health.0 > 0 { continue; }
+
+        animation.animation_id = slime_animations.squash;
+        // Keep the body in place and harmless while the death animation plays
+        commands.entity(hit.enemy)
+            .remove::<(KillZone, MovementDirection)>()
+            .insert((
+                EnemyDying(Timer::from_seconds(0.4, TimerMode::Once)),
+                RigidBody::Kinematic,
+                LinearVelocity::ZERO,
+                CollisionLayers::NONE,
+            ));
+        for child in children.iter() {
+            commands.entity(*child)
+                .remove::<KillZone>()
+                .insert(CollisionLayers::NONE);
+        }
+
+        enemy_killed_event.send(EnemyKilled {
+            enemy: hit.enemy,
+            translation: transform.translation(),
+        });
+    }
+}
+
+pub fn enemy_dying_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut enemy_query: Query<(Entity, &mut EnemyDying, &mut Transform)>,
+) {
+    for (entity, mut dying, mut transform) in enemy_query.iter_mut() {
+        dying.0.tick(time.delta());
+        // Squash the slime flat
+        transform.scale.y = 1.0 - dying.0.fraction() * 0.7;
+        if dying.0.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
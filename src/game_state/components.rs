@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use crate::save::components::UserSettings;
+
+#[derive(States, Default, Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum GameState {
+    #[default]
+    Title,
+    Playing,
+    Paused,
+    LevelComplete,
+    GameOver,
+}
+
+#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
+pub enum MenuAction {
+    Confirm,
+    Continue,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl MenuAction {
+    pub fn default_input_map() -> InputMap<Self> {
+        let mut input_map = InputMap::default();
+
+        input_map.insert(MenuAction::Confirm, KeyCode::Enter);
+        input_map.insert(MenuAction::Confirm, KeyCode::Space);
+        input_map.insert(MenuAction::Confirm, KeyCode::ControlRight);
+        input_map.insert(MenuAction::Confirm, GamepadButtonType::South);
+        input_map.insert(MenuAction::Continue, KeyCode::KeyC);
+        input_map.insert(MenuAction::Continue, GamepadButtonType::North);
+        input_map.insert(MenuAction::Up, KeyCode::ArrowUp);
+        input_map.insert(MenuAction::Up, KeyCode::KeyW);
+        input_map.insert(MenuAction::Up, GamepadButtonType::DPadUp);
+        input_map.insert(MenuAction::Down, KeyCode::ArrowDown);
+        input_map.insert(MenuAction::Down, KeyCode::KeyS);
+        input_map.insert(MenuAction::Down, GamepadButtonType::DPadDown);
+        input_map.insert(MenuAction::Left, KeyCode::ArrowLeft);
+        input_map.insert(MenuAction::Left, KeyCode::KeyA);
+        input_map.insert(MenuAction::Left, GamepadButtonType::DPadLeft);
+        input_map.insert(MenuAction::Right, KeyCode::ArrowRight);
+        input_map.insert(MenuAction::Right, KeyCode::KeyD);
+        input_map.insert(MenuAction::Right, GamepadButtonType::DPadRight);
+
+        return input_map;
+    }
+}
+
+/// The root of the menu shown for the current state, despawned when the state is left.
+#[derive(Component)]
+pub struct MenuScreen;
+
+/// The level to continue with once the level complete screen is confirmed.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct CompletedLevel {
+    pub target_level: Option<String>,
+}
+
+/// A row of the pause menu that changes one of the audio or accessibility settings.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SettingEntry {
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    Mute,
+    ScreenShake,
+    HitStop,
+}
+
+impl SettingEntry {
+    pub const ALL: [SettingEntry; 6] = [
+        SettingEntry::MasterVolume,
+        SettingEntry::MusicVolume,
+        SettingEntry::SfxVolume,
+        SettingEntry::Mute,
+        SettingEntry::ScreenShake,
+        SettingEntry::HitStop,
+    ];
+    pub const VOLUME_STEP: f32 = 0.1;
+
+    pub fn label(&self, user_settings: &UserSettings) -> String {
+        let percent = |volume: f32| (volume * 100.0).round() as i32;
+        let on_off = |enabled: bool| if enabled { "On" } else { "Off" };
+        let audio_settings = &user_settings.audio;
+        let accessibility_settings = &user_settings.accessibility;
+        match self {
+            SettingEntry::MasterVolume => format!("Master {}%", percent(audio_settings.master_volume)),
+            SettingEntry::MusicVolume => format!("Music {}%", percent(audio_settings.music_volume)),
+            SettingEntry::SfxVolume => format!("Sound {}%", percent(audio_settings.sfx_volume)),
+            SettingEntry::Mute => format!("Mute {}", on_off(audio_settings.muted)),
+            SettingEntry::ScreenShake => format!("Screen Shake {}", on_off(accessibility_settings.screen_shake)),
+            SettingEntry::HitStop => format!("Hit-Stop {}", on_off(accessibility_settings.hit_stop)),
+        }
+    }
+
+    /// Whether the entry switches a setting on and off instead of stepping a volume.
+    pub fn is_toggle(&self) -> bool {
+        matches!(self, SettingEntry::Mute | SettingEntry::ScreenShake | SettingEntry::HitStop)
+    }
+
+    /// Moves a volume one step in `direction`, toggles are switched either way.
+    pub fn adjust(&self, user_settings: &mut UserSettings, direction: f32) {
+        let step = |volume: &mut f32| *volume = (*volume + direction * Self::VOLUME_STEP).clamp(0.0, 1.0);
+        let toggle = |enabled: &mut bool| *enabled = !*enabled;
+        let audio_settings = &mut user_settings.audio;
+        let accessibility_settings = &mut user_settings.accessibility;
+        match self {
+            SettingEntry::MasterVolume => step(&mut audio_settings.master_volume),
+            SettingEntry::MusicVolume => step(&mut audio_settings.music_volume),
+            SettingEntry::SfxVolume => step(&mut audio_settings.sfx_volume),
+            SettingEntry::Mute => toggle(&mut audio_settings.muted),
+            SettingEntry::ScreenShake => toggle(&mut accessibility_settings.screen_shake),
+            SettingEntry::HitStop => toggle(&mut accessibility_settings.hit_stop),
+        }
+    }
+}
+
+/// The index into [`SettingEntry::ALL`] highlighted in the pause menu.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct SelectedSetting(pub usize);
@@ -0,0 +1,39 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::InputManagerPlugin;
+use crate::game_state::components::*;
+use crate::game_state::systems::*;
+use crate::world::systems::level_exit_system;
+
+pub mod components;
+mod systems;
+
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_state::<GameState>()
+            .init_resource::<CompletedLevel>()
+            .init_resource::<SelectedSetting>()
+            .add_plugins(InputManagerPlugin::<MenuAction>::default())
+            .add_systems(Startup, setup_menu_input_system)
+            .add_systems(Update, update_physics_time_system.run_if(state_changed::<GameState>))
+            .add_systems(OnEnter(GameState::Title), spawn_menu_screen_system)
+            .add_systems(OnEnter(GameState::Paused), spawn_menu_screen_system)
+            .add_systems(OnEnter(GameState::LevelComplete), spawn_menu_screen_system)
+            .add_systems(OnEnter(GameState::GameOver), spawn_menu_screen_system)
+            .add_systems(OnExit(GameState::Title), despawn_menu_screen_system)
+            .add_systems(OnExit(GameState::Paused), despawn_menu_screen_system)
+            .add_systems(OnExit(GameState::LevelComplete), despawn_menu_screen_system)
+            .add_systems(OnExit(GameState::GameOver), despawn_menu_screen_system)
+            .add_systems(Update, title_screen_system.run_if(in_state(GameState::Title)))
+            .add_systems(Update, pause_system.run_if(in_state(GameState::Playing).or_else(in_state(GameState::Paused))))
+            .add_systems(Update, pause_menu_system.run_if(in_state(GameState::Paused)))
+            .add_systems(Update, update_pause_menu_system.after(pause_menu_system).run_if(in_state(GameState::Paused)))
+            .add_systems(Update, level_complete_system.after(level_exit_system).run_if(in_state(GameState::Playing)))
+            .add_systems(Update, level_complete_screen_system.run_if(in_state(GameState::LevelComplete)))
+            .add_systems(Update, game_over_system.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, game_over_screen_system.run_if(in_state(GameState::GameOver)))
+        ;
+    }
+}
@@ -0,0 +1,256 @@
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use leafwing_input_manager::InputManagerBundle;
+use leafwing_input_manager::prelude::*;
+use crate::game_state::components::*;
+use crate::hud::components::format_elapsed;
+use crate::Player;
+use crate::player::components::{DeathSettings, DeathTimer, Lives, PlayerAction, PlayerSlot, RespawnPlayerEvent};
+use crate::save::components::{LevelResult, SaveGame, UserSettings};
+use crate::world::components::{GameFonts, IsDead, LevelExitReached, LevelTransition};
+
+pub fn setup_menu_input_system(
+    mut commands: Commands,
+) {
+    commands.spawn((
+        InputManagerBundle::with_map(MenuAction::default_input_map()),
+        Name::new("MenuInput")
+    ));
+}
+
+/// Physics only advances while playing.
+pub fn update_physics_time_system(
+    state: Res<State<GameState>>,
+    mut physics_time: ResMut<Time<Physics>>,
+) {
+    if *state.get() == GameState::Playing {
+        physics_time.unpause();
+    } else {
+        physics_time.pause();
+    }
+}
+
+pub fn spawn_menu_screen_system(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    game_fonts: Res<GameFonts>,
+    level_result: Option<Res<LevelResult>>,
+    user_settings: Res<UserSettings>,
+) {
+    let (title, hint) = match state.get() {
+        GameState::Title if SaveGame::load().is_some() => ("Bevycation", "Press jump to start a new game\nPress C to continue"),
+        GameState::Title => ("Bevycation", "Press jump to start"),
+        GameState::Paused => ("Paused", "Press pause to resume"),
+        GameState::LevelComplete => ("Level Complete", "Press jump to continue"),
+        GameState::GameOver => ("Game Over", "Press jump to try again"),
+        GameState::Playing => return,
+    };
+
+    let mut details = Vec::new();
+    if let (GameState::LevelComplete, Some(level_result)) = (state.get(), &level_result) {
+        details.push(match level_result.new_max_coins {
+            true => format!("Coins {}/{}  New record!", level_result.stats.coins_collected, level_result.stats.coins_total),
+            false => format!("Coins {}/{}  Best {}", level_result.stats.coins_collected, level_result.stats.coins_total, level_result.record.max_coins),
+        });
+        details.push(match (level_result.new_best_time, level_result.record.best_time) {
+            (false, Some(best_time)) => format!("Time {}  Best {}", format_elapsed(level_result.stats.elapsed), format_elapsed(best_time)),
+            _ => format!("Time {}  New best!", format_elapsed(level_result.stats.elapsed)),
+        });
+        details.push(format!("Deaths {}", level_result.stats.deaths));
+    }
+
+    commands.spawn((
+        Name::new("Menu Screen"),
+        MenuScreen,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(24.0),
+                ..default()
+            },
+            background_color: Color::BLACK.with_a(0.6).into(),
+            z_index: ZIndex::Global(50),
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(title, TextStyle {
+            font: game_fonts.pixelated_bold_font.clone_weak(),
+            font_size: 48.0,
+            color: Color::WHITE,
+        }));
+        for detail in details {
+            parent.spawn(TextBundle::from_section(detail, TextStyle {
+                font: game_fonts.pixelated_font.clone_weak(),
+                font_size: 20.0,
+                color: Color::WHITE,
+            }));
+        }
+        if *state.get() == GameState::Paused {
+            for entry in SettingEntry::ALL {
+                parent.spawn((entry, TextBundle::from_section(entry.label(&user_settings), TextStyle {
+                    font: game_fonts.pixelated_font.clone_weak(),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                })));
+            }
+        }
+        parent.spawn(TextBundle::from_section(hint, TextStyle {
+            font: game_fonts.pixelated_font.clone_weak(),
+            font_size: 16.0,
+            color: Color::GRAY,
+        }));
+    });
+}
+
+pub fn despawn_menu_screen_system(
+    mut commands: Commands,
+    menu_query: Query<Entity, With<MenuScreen>>,
+) {
+    for entity in menu_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn title_screen_system(
+    mut commands: Commands,
+    menu_input_query: Query<&ActionState<MenuAction>>,
+    mut lives: ResMut<Lives>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Ok(input) = menu_input_query.get_single() else { return };
+    if input.just_pressed(&MenuAction::Confirm) {
+        lives.reset();
+        next_state.set(GameState::Playing);
+    } else if input.just_pressed(&MenuAction::Continue) {
+        // Continue is only offered for a save that can be used
+        let Some(save_game) = SaveGame::load() else { return };
+        commands.add(move |world: &mut World| save_game.apply(world));
+        lives.reset();
+        next_state.set(GameState::Playing);
+    }
+}
+
+pub fn pause_system(
+    player_input_query: Query<&ActionState<PlayerAction>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !player_input_query.iter().any(|input| input.just_pressed(&PlayerAction::Pause)) { return; }
+
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+pub fn level_complete_system(
+    mut level_exit_reached: EventReader<LevelExitReached>,
+    mut completed_level: ResMut<CompletedLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(level_exit) = level_exit_reached.read().last() else { return };
+    completed_level.target_level = Some(level_exit.target_level.clone());
+    next_state.set(GameState::LevelComplete);
+}
+
+pub fn level_complete_screen_system(
+    mut commands: Commands,
+    menu_input_query: Query<&ActionState<MenuAction>>,
+    mut completed_level: ResMut<CompletedLevel>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Ok(input) = menu_input_query.get_single() else { return };
+    if !input.just_pressed(&MenuAction::Confirm) { return; }
+
+    if let Some(target_level) = completed_level.target_level.take() {
+        commands.insert_resource(LevelTransition::new(target_level));
+    }
+    next_state.set(GameState::Playing);
+}
+
+/// The game is over once everyone is dead and nobody comes back on their own.
+pub fn game_over_system(
+    death_settings: Res<DeathSettings>,
+    lives: Res<Lives>,
+    player_query: Query<(Has<IsDead>, Has<DeathTimer>), With<Player>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if player_query.is_empty() { return; }
+    if death_settings.auto_respawn && lives.remaining > 0 { return; }
+
+    let all_dead = player_query.iter().all(|(is_dead, is_dying)| is_dead && !is_dying);
+    if all_dead {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+pub fn game_over_screen_system(
+    menu_input_query: Query<&ActionState<MenuAction>>,
+    player_query: Query<&PlayerSlot, (With<Player>, With<IsDead>)>,
+    mut lives: ResMut<Lives>,
+    mut respawn_player_event: EventWriter<RespawnPlayerEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Ok(input) = menu_input_query.get_single() else { return };
+    if !input.just_pressed(&MenuAction::Confirm) { return; }
+
+    lives.reset();
+    for slot in player_query.iter() {
+        respawn_player_event.send(RespawnPlayerEvent { slot: *slot });
+    }
+    next_state.set(GameState::Playing);
+}
+
+pub fn pause_menu_system(
+    menu_input_query: Query<&ActionState<MenuAction>>,
+    mut selected: ResMut<SelectedSetting>,
+    mut user_settings: ResMut<UserSettings>,
+) {
+    let Ok(input) = menu_input_query.get_single() else { return };
+    let entry_count = SettingEntry::ALL.len();
+
+    if input.just_pressed(&MenuAction::Up) {
+        selected.0 = (selected.0 + entry_count - 1) % entry_count;
+    }
+    if input.just_pressed(&MenuAction::Down) {
+        selected.0 = (selected.0 + 1) % entry_count;
+    }
+
+    let entry = SettingEntry::ALL[selected.0];
+    let direction = if input.just_pressed(&MenuAction::Left) {
+        -1.0
+    } else if input.just_pressed(&MenuAction::Right) {
+        1.0
+    } else if input.just_pressed(&MenuAction::Confirm) && entry.is_toggle() {
+        1.0
+    } else {
+        return;
+    };
+    entry.adjust(&mut user_settings, direction);
+}
+
+pub fn update_pause_menu_system(
+    selected: Res<SelectedSetting>,
+    user_settings: Res<UserSettings>,
+    mut entry_query: Query<(&SettingEntry, &mut Text)>,
+    spawned_entry_query: Query<(), Added<SettingEntry>>,
+) {
+    // Entries are spawned without the selection marker
+    if !selected.is_changed() && !user_settings.is_changed() && spawned_entry_query.is_empty() { return; }
+
+    let selected_entry = SettingEntry::ALL[selected.0];
+    for (entry, mut text) in entry_query.iter_mut() {
+        let is_selected = *entry == selected_entry;
+        text.sections[0].value = match is_selected {
+            true => format!("> {} <", entry.label(&user_settings)),
+            false => entry.label(&user_settings),
+        };
+        text.sections[0].style.color = if is_selected { Color::WHITE } else { Color::GRAY };
+    }
+}
@@ -0,0 +1,49 @@
+use bevy::prelude::*;
+
+/// Progress in the current level, reset when another level is selected.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct LevelStats {
+    pub coins_collected: i32,
+    /// Coins in the level, collected ones included
+    pub coins_total: i32,
+    /// Seconds spent playing the level
+    pub elapsed: f32,
+    pub deaths: u32,
+}
+
+/// Formats seconds as `minutes:seconds.tenths`.
+pub fn format_elapsed(seconds: f32) -> String {
+    let minutes = (seconds / 60.0).floor();
+    format!("{:02}:{:04.1}", minutes, seconds - minutes * 60.0)
+}
+
+#[derive(Component)]
+pub struct Hud;
+
+#[derive(Component)]
+pub struct HudCoinText;
+
+#[derive(Component)]
+pub struct HudInventoryText;
+
+#[derive(Component)]
+pub struct HudTimeText;
+
+#[derive(Component)]
+pub struct HudDeathText;
+
+/// Makes a HUD element pop, restarted on every pickup.
+#[derive(Component)]
+pub struct HudPulse {
+    pub timer: Timer,
+}
+
+impl HudPulse {
+    pub const DURATION: f32 = 0.3;
+
+    pub fn finished() -> Self {
+        let mut timer = Timer::from_seconds(Self::DURATION, TimerMode::Once);
+        timer.tick(timer.duration());
+        HudPulse { timer }
+    }
+}
@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+use crate::game_state::components::GameState;
+use crate::hud::components::*;
+use crate::hud::systems::*;
+use crate::world::components::CurrentLevel;
+use crate::world::systems::update_current_level_system;
+
+pub mod components;
+mod systems;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<LevelStats>()
+            .add_systems(Startup, setup_hud_system)
+            .add_systems(Update, hud_visibility_system.run_if(state_changed::<GameState>))
+            .add_systems(Update, reset_level_stats_system.after(update_current_level_system).run_if(resource_changed::<CurrentLevel>))
+            .add_systems(Update, reset_collected_coins_system.after(reset_level_stats_system))
+            .add_systems(Update, level_time_system.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, count_deaths_system.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, count_coins_system.after(reset_collected_coins_system))
+            .add_systems(Update, update_hud_text_system.after(count_coins_system).after(level_time_system).after(count_deaths_system))
+            .add_systems(Update, hud_pulse_system)
+        ;
+    }
+}
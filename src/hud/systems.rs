@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::{LevelEvent, LevelIid};
+use crate::{Inventory, Player};
+use crate::game_state::components::GameState;
+use crate::hud::components::*;
+use crate::player::components::CoinCollected;
+use crate::world::components::{Coin, CurrentLevel, GameFonts, IsDead};
+
+const HUD_FONT_SIZE: f32 = 16.0;
+const PULSE_COLOR: Vec3 = Vec3::new(1.0, 0.85, 0.2);
+
+pub fn setup_hud_system(
+    mut commands: Commands,
+    game_fonts: Res<GameFonts>,
+) {
+    let text_style = TextStyle {
+        font: game_fonts.pixelated_font.clone_weak(),
+        font_size: HUD_FONT_SIZE,
+        color: Color::WHITE,
+    };
+
+    commands.spawn((
+        Name::new("Hud"),
+        Hud,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(16.0),
+                left: Val::Px(16.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn((HudCoinText, HudPulse::finished(), TextBundle::from_section("", text_style.clone())));
+        parent.spawn((HudInventoryText, TextBundle::from_section("", text_style.clone())));
+        parent.spawn((HudTimeText, TextBundle::from_section("", text_style.clone())));
+        parent.spawn((HudDeathText, TextBundle::from_section("", text_style)));
+    });
+}
+
+/// The HUD is hidden behind the title screen.
+pub fn hud_visibility_system(
+    state: Res<State<GameState>>,
+    mut hud_query: Query<&mut Visibility, With<Hud>>,
+) {
+    let Ok(mut visibility) = hud_query.get_single_mut() else { return };
+    *visibility = match state.get() {
+        GameState::Title => Visibility::Hidden,
+        _ => Visibility::Inherited,
+    };
+}
+
+pub fn reset_level_stats_system(
+    mut level_stats: ResMut<LevelStats>,
+) {
+    *level_stats = LevelStats::default();
+}
+
+/// A restarted level brings its coins back.
+pub fn reset_collected_coins_system(
+    mut level_events: EventReader<LevelEvent>,
+    current_level: Res<CurrentLevel>,
+    mut level_stats: ResMut<LevelStats>,
+) {
+    for level_event in level_events.read() {
+        let LevelEvent::Spawned(level_iid) = level_event else { continue };
+        if current_level.0.as_ref() == Some(&level_iid.to_string()) {
+            level_stats.coins_collected = 0;
+        }
+    }
+}
+
+pub fn level_time_system(
+    time: Res<Time>,
+    mut level_stats: ResMut<LevelStats>,
+) {
+    level_stats.elapsed += time.delta_seconds();
+}
+
+pub fn count_deaths_system(
+    died_query: Query<(), (With<Player>, Added<IsDead>)>,
+    mut level_stats: ResMut<LevelStats>,
+) {
+    let deaths = died_query.iter().count() as u32;
+    if deaths > 0 {
+        level_stats.deaths += deaths;
+    }
+}
+
+pub fn count_coins_system(
+    mut coin_collected_events: EventReader<CoinCollected>,
+    coin_query: Query<Entity, With<Coin>>,
+    current_level: Res<CurrentLevel>,
+    parent_query: Query<&Parent>,
+    level_query: Query<&LevelIid>,
+    mut level_stats: ResMut<LevelStats>,
+    mut pulse_query: Query<&mut HudPulse, With<HudCoinText>>,
+) {
+    let mut coins_collected = level_stats.coins_collected;
+    for coin_event in coin_collected_events.read() {
+        coins_collected += coin_event.amount_collected;
+        for mut pulse in pulse_query.iter_mut() {
+            pulse.timer.reset();
+        }
+    }
+
+    let coins_remaining = coin_query.iter()
+        .filter(|coin| current_level.contains(*coin, &parent_query, &level_query))
+        .count() as i32;
+    let coins_total = coins_remaining + coins_collected;
+
+    // Only touch the stats when they change, the HUD text follows them
+    if level_stats.coins_collected != coins_collected || level_stats.coins_total != coins_total {
+        level_stats.coins_collected = coins_collected;
+        level_stats.coins_total = coins_total;
+    }
+}
+
+pub fn update_hud_text_system(
+    level_stats: Res<LevelStats>,
+    inventory_query: Query<Ref<Inventory>, With<Player>>,
+    mut coin_text_query: Query<&mut Text, (With<HudCoinText>, Without<HudInventoryText>, Without<HudTimeText>, Without<HudDeathText>)>,
+    mut inventory_text_query: Query<&mut Text, (With<HudInventoryText>, Without<HudTimeText>, Without<HudDeathText>)>,
+    mut time_text_query: Query<&mut Text, (With<HudTimeText>, Without<HudDeathText>)>,
+    mut death_text_query: Query<&mut Text, With<HudDeathText>>,
+) {
+    if !level_stats.is_changed() && !inventory_query.iter().any(|inventory| inventory.is_changed()) { return; }
+
+    if let Ok(mut text) = coin_text_query.get_single_mut() {
+        set_text(&mut text, format!("Coins {}/{}", level_stats.coins_collected, level_stats.coins_total));
+    }
+    if let Ok(mut text) = inventory_text_query.get_single_mut() {
+        let total_collected: i32 = inventory_query.iter().map(|inventory| inventory.collected_coins).sum();
+        set_text(&mut text, format!("Total {}", total_collected));
+    }
+    if let Ok(mut text) = time_text_query.get_single_mut() {
+        set_text(&mut text, format!("Time {}", format_elapsed(level_stats.elapsed)));
+    }
+    if let Ok(mut text) = death_text_query.get_single_mut() {
+        set_text(&mut text, format!("Deaths {}", level_stats.deaths));
+    }
+}
+
+/// Leaves the text untouched if it already shows `value`, so it isn't laid out again.
+fn set_text(text: &mut Mut<Text>, value: String) {
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+pub fn hud_pulse_system(
+    time: Res<Time<Real>>,
+    mut pulse_query: Query<(&mut HudPulse, &mut Transform, &mut Text)>,
+) {
+    for (mut pulse, mut transform, mut text) in pulse_query.iter_mut() {
+        pulse.timer.tick(time.delta());
+        // The frame a pulse finishes puts the element back to rest, after that it is left alone
+        if pulse.timer.finished() && !pulse.timer.just_finished() { continue; }
+        let strength = pulse.timer.fraction_remaining();
+        transform.scale = Vec3::splat(1.0 + 0.4 * strength);
+        let color = Vec3::ONE.lerp(PULSE_COLOR, strength);
+        for section in text.sections.iter_mut() {
+            section.style.color = Color::rgb(color.x, color.y, color.z);
+        }
+    }
+}
@@ -1,29 +1,46 @@
+mod camera;
 mod character_controller;
 mod world;
 mod player;
 mod debugging;
 mod enemy;
+mod game_state;
+mod hud;
+mod save;
+mod sfx;
 
+use bevy::audio::SpatialScale;
 use bevy::prelude::*;
-use bevy::transform::TransformSystem;
 use bevy::utils::HashSet;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_editor_pls::EditorPlugin;
 use bevy_spritesheet_animation::prelude::SpritesheetAnimationPlugin;
 use bevy_vector_shapes::prelude::*;
 use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::camera::CameraPlugin;
+use crate::camera::components::CameraRig;
 use crate::character_controller::CharacterControllerPlugin;
 use crate::debugging::DebuggingPlugin;
 use crate::enemy::EnemyPlugin;
+use crate::game_state::GameStatePlugin;
+use crate::hud::HudPlugin;
+use crate::save::SavePlugin;
+use crate::sfx::SfxPlugin;
 use crate::player::PlayerPlugin;
-use crate::world::components::{GameSounds, IsDead};
+use crate::world::components::{AudioSettings, BackgroundMusic, GameSounds};
 use crate::world::WorldPlugin;
 
 fn main() {
     App::new()
         .register_type::<EntityInstance>()
         .register_type::<HashSet<Entity>>()
-        .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest())) // prevents blurry sprites
+        .add_plugins(DefaultPlugins
+            .set(ImagePlugin::default_nearest()) // prevents blurry sprites
+            .set(AudioPlugin {
+                default_spatial_scale: SpatialScale::new_2d(AUDIO_SPATIAL_SCALE),
+                ..default()
+            }))
         .add_plugins(PhysicsPlugins::default())
         .add_plugins(Shape2dPlugin::default())
         .add_plugins(LdtkPlugin)
@@ -38,25 +55,32 @@ fn main() {
         })
         .add_plugins(EditorPlugin::default())
         .add_plugins(DebuggingPlugin)
+        .add_plugins(GameStatePlugin)
         .add_plugins(CharacterControllerPlugin)
         .add_plugins(WorldPlugin)
         .add_plugins(PlayerPlugin)
         .add_plugins(EnemyPlugin)
+        .add_plugins(HudPlugin)
+        .add_plugins(SavePlugin)
+        .add_plugins(SfxPlugin)
+        .add_plugins(CameraPlugin)
         .add_systems(Startup, setup_system)
-        .add_systems(Update, zoom_scale_system)
-        .add_systems(PostUpdate, camera_follow_player_system.after(PhysicsSet::Sync).before(TransformSystem::TransformPropagate))
         .insert_resource(Msaa::Off)
-        .insert_resource(GlobalVolume::new(0.2))
         .run();
 }
 
 #[derive(Component)]
 pub struct MainCamera;
 
+/// Converts world units (pixels) into the units positional audio falls off with.
+const AUDIO_SPATIAL_SCALE: f32 = 1.0 / 100.0;
+/// The distance between the listener's ears in world units, wide enough to pan sounds on screen.
+const SPATIAL_LISTENER_GAP: f32 = 100.0;
+
 #[derive(Component, Reflect)]
 pub struct Player;
 
-#[derive(Component, Reflect, Default)]
+#[derive(Component, Reflect, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct Inventory {
     pub collected_coins: i32,
 }
@@ -65,6 +89,7 @@ fn setup_system(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     game_sounds: Res<GameSounds>,
+    audio_settings: Res<AudioSettings>,
 ) {
     let ldtk_handle = asset_server.load("first_game.ldtk");
     commands.spawn((
@@ -79,33 +104,16 @@ fn setup_system(
     commands.spawn((
         Camera2dBundle::default(),
         MainCamera,
+        CameraRig::default(),
+        SpatialListener::new(SPATIAL_LISTENER_GAP),
     ));
 
     //background music
     commands.spawn((
         Name::new("Background Music"),
+        BackgroundMusic,
         AudioBundle {
             source: game_sounds.background_music.clone(),
-            ..default()
+            settings: audio_settings.music_playback(),
         }));
 }
-
-pub fn camera_follow_player_system(
-    q_player: Query<&Transform, (With<Player>, Without<IsDead>)>,
-    mut q_camera: Query<&mut Transform, (With<MainCamera>, Without<Player>)>,
-) {
-    let Ok(player_transform) = q_player.get_single() else { return; };
-    let mut camera_transform = q_camera.single_mut();
-
-    camera_transform.translation = player_transform.translation + Vec3::Y * 50.0;
-}
-
-fn zoom_scale_system(
-    mut query_camera: Query<&mut OrthographicProjection, Added<MainCamera>>,
-) {
-    //if we scale the projection at creation, playersprite won't be rendered
-    for mut projection in query_camera.iter_mut() {
-        projection.scale = 0.4;
-    }
-}
-
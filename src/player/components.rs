@@ -8,35 +8,145 @@ pub enum PlayerAction {
     Run,
     Jump,
     DropDown,
-    Respawn
+    Respawn,
+    Pause,
 }
 
 impl PlayerAction {
     pub fn default_input_map() -> InputMap<Self> {
+        let mut input_map = Self::gamepad_input_map();
+
+        input_map.insert(PlayerAction::Move, VirtualDPad::wasd());
+        input_map.insert(PlayerAction::Jump, KeyCode::Space);
+        input_map.insert(PlayerAction::Respawn, KeyCode::Space);
+        input_map.insert_chord(PlayerAction::DropDown, [KeyCode::Space, KeyCode::KeyS]);
+        input_map.insert(PlayerAction::Pause, KeyCode::Escape);
+        input_map.set_gamepad(Gamepad::new(0));
+
+        return input_map;
+    }
+
+    /// Input map for the second local player, using the arrow keys and right control.
+    pub fn second_player_input_map() -> InputMap<Self> {
+        let mut input_map = Self::gamepad_input_map();
+
+        input_map.insert(PlayerAction::Move, VirtualDPad::arrow_keys());
+        input_map.insert(PlayerAction::Jump, KeyCode::ControlRight);
+        input_map.insert(PlayerAction::Respawn, KeyCode::ControlRight);
+        input_map.insert_chord(PlayerAction::DropDown, [KeyCode::ControlRight, KeyCode::ArrowDown]);
+        input_map.set_gamepad(Gamepad::new(1));
+
+        return input_map;
+    }
+
+    pub fn input_map_for_slot(slot: PlayerSlot) -> InputMap<Self> {
+        match slot.0 {
+            0 => Self::default_input_map(),
+            _ => Self::second_player_input_map(),
+        }
+    }
+
+    fn gamepad_input_map() -> InputMap<Self> {
         let mut input_map = InputMap::default();
 
         input_map.insert(PlayerAction::Move, DualAxis::left_stick());
         input_map.insert(PlayerAction::Jump, GamepadButtonType::South);
-        input_map.insert(PlayerAction::Respawn, GamepadButtonType::Start);
+        // Start pauses the game, respawning is done with the jump button like on the keyboard
         input_map.insert(PlayerAction::Respawn, GamepadButtonType::South);
+        input_map.insert(PlayerAction::Pause, GamepadButtonType::Start);
 
 
         input_map.insert_chord(PlayerAction::DropDown, [
             InputKind::from(SingleAxis::negative_only(GamepadAxisType::LeftStickY, 0.3)),
             InputKind::from(GamepadButtonType::South)]
         );
-        input_map.insert(PlayerAction::Move, VirtualDPad::wasd());
-        input_map.insert(PlayerAction::Jump, KeyCode::Space);
-        input_map.insert(PlayerAction::Respawn, KeyCode::Space);
-        input_map.insert_chord(PlayerAction::DropDown, [KeyCode::Space, KeyCode::KeyS]);
 
         return input_map;
     }
 }
 
+/// The number of local players that can join the game.
+pub const MAX_PLAYERS: usize = 2;
+
+/// Identifies which local player an input entity or a player entity belongs to.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Hash, Debug, Reflect)]
+pub struct PlayerSlot(pub usize);
+
+/// A marker component on input entities whose player has joined the game.
+#[derive(Component)]
+pub struct Joined;
+
 #[derive(Component)]
 pub struct PlayerVisual;
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Component, Reflect)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Health {
+    pub fn new(max: u32) -> Self {
+        Health { current: max, max }
+    }
+}
+
+/// The speed a player is pushed back with when taking damage.
+#[derive(Copy, Clone, PartialEq, Debug, Component)]
+pub struct Knockback(pub f32);
+
+/// Controls what happens after a player died.
+#[derive(Resource, Clone, Debug)]
+pub struct DeathSettings {
+    /// How fast time runs while the death sequence plays.
+    /// Physics follows virtual time, so it slows down as well.
+    pub slow_motion_speed: f32,
+    /// Seconds of game time the death sequence lasts, stretched by the slow motion like the death animation
+    pub respawn_delay: f32,
+    /// Respawns once the death sequence is over, otherwise waits for [`PlayerAction::Respawn`]
+    pub auto_respawn: bool,
+}
+
+impl Default for DeathSettings {
+    fn default() -> Self {
+        DeathSettings {
+            slow_motion_speed: 0.4,
+            // Holds the last frame of the death animation for a moment
+            respawn_delay: PlayerAnimations::DEATH_ANIMATION_SECONDS + 0.1,
+            auto_respawn: true,
+        }
+    }
+}
+
+/// Lives shared between all players, every death uses one up.
+/// The game is over once the last one is used up and everyone is dead.
+#[derive(Resource, Clone, Debug)]
+pub struct Lives {
+    pub remaining: u32,
+    pub max: u32,
+}
+
+impl Default for Lives {
+    fn default() -> Self {
+        Lives { remaining: 3, max: 3 }
+    }
+}
+
+impl Lives {
+    pub fn reset(&mut self) {
+        self.remaining = self.max;
+    }
+}
+
+/// The remaining death sequence of a dead player.
+#[derive(Clone, Debug, Component)]
+pub struct DeathTimer(pub Timer);
+
+/// A player that recently took damage and can't be hurt again until the timer runs out.
+#[derive(Clone, Debug, Component)]
+#[component(storage = "SparseSet")]
+pub struct Invulnerable(pub Timer);
+
 #[derive(Resource)]
 pub struct PlayerAnimations {
     pub texture: Handle<Image>,
@@ -44,6 +154,15 @@ pub struct PlayerAnimations {
     pub idle_animation: AnimationId,
     pub run_animation: AnimationId,
     pub jump_animation: AnimationId,
+    pub wall_slide_animation: AnimationId,
+    pub death_animation: AnimationId,
+}
+
+impl PlayerAnimations {
+    const DEATH_FRAME_COUNT: usize = 4;
+    const DEATH_FRAME_MILLIS: u32 = 150;
+    /// How long the death animation plays in game time.
+    pub const DEATH_ANIMATION_SECONDS: f32 = (Self::DEATH_FRAME_COUNT as u32 * Self::DEATH_FRAME_MILLIS) as f32 / 1000.0;
 }
 
 impl FromWorld for PlayerAnimations {
@@ -91,20 +210,54 @@ impl FromWorld for PlayerAnimations {
                 .set_repeat(AnimationRepeat::Loop);
         });
 
+        let wall_slide_clip_id = library.new_clip(|clip| {
+            clip.push_frame_indices(sprite_sheet.positions([(0, 6)]));
+        });
+
+        let wall_slide_animation_id = library.new_animation(|animation| {
+            animation
+                .add_stage(wall_slide_clip_id.into())
+                .set_repeat(AnimationRepeat::Loop);
+        });
+
+        let death_clip_id = library.new_clip(|clip| {
+            clip.push_frame_indices(sprite_sheet.horizontal_strip(0, 7, PlayerAnimations::DEATH_FRAME_COUNT));
+            clip.set_default_duration(AnimationDuration::PerFrame(PlayerAnimations::DEATH_FRAME_MILLIS));
+        });
+
+        let death_animation_id = library.new_animation(|animation| {
+            animation
+                .add_stage(death_clip_id.into())
+                .set_repeat(AnimationRepeat::Cycles(1));
+        });
+
         PlayerAnimations {
             texture: texture,
             layout: texture_atlas_layout,
             idle_animation: idle_animation_id,
             run_animation: run_animation_id,
             jump_animation: jump_animation_id,
+            wall_slide_animation: wall_slide_animation_id,
+            death_animation: death_animation_id,
         }
     }
 }
 
 
+/// Requests a dead player to be brought back into the game.
+#[derive(Event)]
+pub struct RespawnPlayerEvent {
+    pub slot: PlayerSlot,
+}
+
+/// Sent when a player loses health, including the fatal hit.
+#[derive(Event)]
+pub struct PlayerHurt;
+
 #[derive(Event)]
 pub struct CoinCollected {
-    #[allow(dead_code)]
     pub amount_collected: i32,
     pub total_collected: i32,
+    /// Where the coin was, if it has a [`SpatialSound`](crate::sfx::components::SpatialSound)
+    pub position: Option<Vec3>,
 }
\ No newline at end of file
@@ -1,8 +1,11 @@
 use bevy::app::App;
 use bevy::prelude::*;
 use leafwing_input_manager::prelude::*;
+use crate::character_controller::CharacterControllerSet;
 use crate::player::components::*;
 use crate::player::systems::*;
+use crate::game_state::components::GameState;
+use crate::world::systems::{kill_zone_system, update_current_level_system};
 
 pub mod components;
 mod systems;
@@ -13,15 +16,26 @@ impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app
             .init_resource::<PlayerAnimations>()
+            .init_resource::<DeathSettings>()
+            .init_resource::<Lives>()
             .add_event::<CoinCollected>()
+            .add_event::<RespawnPlayerEvent>()
+            .add_event::<PlayerHurt>()
             .register_type::<InputMap<PlayerAction>>()
+            .register_type::<Health>()
             .add_plugins(InputManagerPlugin::<PlayerAction>::default())
             .add_systems(Startup, setup_player_input_system)
+            .add_systems(Update, join_player_system.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, player_intent_system.before(CharacterControllerSet).run_if(in_state(GameState::Playing)))
             .add_systems(Update, spawn_player_system)
-            .add_systems(Update, spawn_player_at_start_system)
-            .add_systems(Update, restart_level_on_input_system)
+            .add_systems(Update, spawn_player_at_start_system.after(update_current_level_system))
+            .add_systems(Update, respawn_on_input_system.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, death_sequence_system.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, respawn_player_system.after(respawn_on_input_system).after(death_sequence_system).run_if(in_state(GameState::Playing)))
             .add_systems(Update, player_animation_system)
-            .add_systems(Update, coin_collection_system)
+            .add_systems(Update, coin_collection_system.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, invulnerability_system.run_if(in_state(GameState::Playing)))
+            .add_systems(PostUpdate, player_damage_system.after(kill_zone_system).run_if(in_state(GameState::Playing)))
         ;
     }
 }
\ No newline at end of file
@@ -1,49 +1,118 @@
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_spritesheet_animation::prelude::*;
-use bevy_xpbd_2d::math::Scalar;
+use bevy_xpbd_2d::math::{Scalar, Vector};
 use bevy_xpbd_2d::prelude::*;
 use leafwing_input_manager::action_state::ActionState;
 use leafwing_input_manager::InputManagerBundle;
 use crate::{Inventory, Player};
 use crate::character_controller::components::*;
+use crate::enemy::components::Stomper;
+use crate::sfx::components::SpatialSound;
 use crate::player::components::*;
 use crate::world::components::*;
 
 pub fn setup_player_input_system(
     mut commands: Commands,
 ) {
-    commands.spawn((
-        InputManagerBundle::with_map(PlayerAction::default_input_map()),
-        Name::new("Input")
-    ));
+    for slot in (0..MAX_PLAYERS).map(PlayerSlot) {
+        let mut input = commands.spawn((
+            InputManagerBundle::with_map(PlayerAction::input_map_for_slot(slot)),
+            slot,
+            Name::new(format!("Input {}", slot.0 + 1)),
+        ));
+        // The first player is always in the game, others drop in when pressing jump
+        if slot.0 == 0 {
+            input.insert(Joined);
+        }
+    }
+}
+
+/// Lets additional local players drop in by pressing jump.
+pub fn join_player_system(
+    mut commands: Commands,
+    input_query: Query<(Entity, &PlayerSlot, &ActionState<PlayerAction>), Without<Joined>>,
+    start_point_query: Query<(Entity, &GlobalTransform), With<PlayerStartPoint>>,
+    current_level: Res<CurrentLevel>,
+    parent_query: Query<&Parent>,
+    level_query: Query<&LevelIid>,
+    mut spawn_player_event: EventWriter<SpawnPlayerEvent>,
+) {
+    let Some((_, start_point)) = start_point_query.iter()
+        .find(|(entity, _)| current_level.contains(*entity, &parent_query, &level_query)) else { return };
+
+    for (entity, slot, input) in input_query.iter() {
+        if !input.just_pressed(&PlayerAction::Jump) { continue; }
+        commands.entity(entity).insert(Joined);
+        spawn_player_event.send(SpawnPlayerEvent {
+            translation: start_point.translation(),
+            slot: *slot,
+            keep_inventory: false,
+        });
+    }
+}
+
+/// Translates the player input into the [`MovementIntent`] of the matching player.
+pub fn player_intent_system(
+    input_query: Query<(&PlayerSlot, &ActionState<PlayerAction>)>,
+    mut player_query: Query<(&PlayerSlot, &mut MovementIntent), With<Player>>,
+) {
+    for (player_slot, mut intent) in player_query.iter_mut() {
+        let Some((_, input)) = input_query.iter().find(|(slot, _)| *slot == player_slot) else { continue };
+
+        let direction = if input.pressed(&PlayerAction::Move) {
+            input.clamped_axis_pair(&PlayerAction::Move).map_or(0.0, |axis| axis.x())
+        } else {
+            0.0
+        };
+
+        *intent = MovementIntent {
+            direction,
+            jump_just_pressed: input.just_pressed(&PlayerAction::Jump),
+            jump_just_released: input.just_released(&PlayerAction::Jump),
+            drop_down: input.just_pressed(&PlayerAction::DropDown),
+        };
+    }
 }
 
 
 pub fn spawn_player_system(
     mut commands: Commands,
-    player_query: Query<(Entity, Has<IsDead>), With<Player>>,
+    player_query: Query<(Entity, &PlayerSlot, &Inventory, Has<IsDead>), With<Player>>,
     mut player_spawn_event: EventReader<SpawnPlayerEvent>,
+    carried_inventories: Res<CarriedInventories>,
     player_animation: Res<PlayerAnimations>,
 ) {
-    for spawn_event in player_spawn_event.read() {
-        if let Ok((player_entity, is_dead)) = player_query.get_single() {
+    'events: for spawn_event in player_spawn_event.read() {
+        let mut inventory = carried_inventories.0.get(&spawn_event.slot).cloned().unwrap_or_default();
+        for (player_entity, slot, previous_inventory, is_dead) in player_query.iter() {
+            if *slot != spawn_event.slot { continue; }
             if is_dead {
+                if spawn_event.keep_inventory {
+                    inventory = previous_inventory.clone();
+                }
                 commands.entity(player_entity).despawn_recursive();
             } else {
-                return;
+                continue 'events;
             }
         }
 
+        // Tint additional players so they can be told apart
+        let color = match spawn_event.slot.0 {
+            0 => Color::WHITE,
+            _ => Color::rgb(0.7, 0.85, 1.0),
+        };
+
         // Use only the subset of sprites in the sheet that make up the run animation
         commands.spawn((
             TransformBundle::from_transform(
                 Transform::from_translation(spawn_event.translation),
             ),
             InheritedVisibility::default(),
-            Name::new("Player"),
+            Name::new(format!("Player {}", spawn_event.slot.0 + 1)),
             Player,
-            Inventory::default(),
+            spawn_event.slot,
+            (inventory, Health::new(3), Knockback(250.0)),
             CharacterControllerBundle::new(Collider::capsule(5.0, 5.0)).with_movement(
                 2000.0,
                 1000.0,
@@ -51,17 +120,27 @@ pub fn spawn_player_system(
                 0.89,
                 350.0,
                 (30.0 as Scalar).to_radians(),
+            ).with_jump_assist(0.1, 0.15),
+            (
+                JumpCutFactor(0.5),
+                FallGravityMultiplier(1.5),
+                WallMovementBundle::new(2.0, 40.0, Vector::new(220.0, 300.0)),
+                Stomper { bounce_impulse: 250.0 },
             ),
             Friction::new(1.0).with_combine_rule(CoefficientCombine::Min),
             Restitution::ZERO.with_combine_rule(CoefficientCombine::Min),
             ColliderDensity(2.0),
             GravityScale(100.0),
             PassThroughOneWayPlatform::ByNormal,
-            CollisionLayers::new(GamePhysicsLayer::Player, [GamePhysicsLayer::Enemy, GamePhysicsLayer::Ground, GamePhysicsLayer::KillZone, GamePhysicsLayer::Collectible])
+            CollisionLayers::new(GamePhysicsLayer::Player, [GamePhysicsLayer::Enemy, GamePhysicsLayer::Ground, GamePhysicsLayer::KillZone, GamePhysicsLayer::Collectible, GamePhysicsLayer::Trigger])
 
         )).with_children(|commands| {
             commands.spawn((
                 SpriteSheetBundle {
+                    sprite: Sprite {
+                        color,
+                        ..default()
+                    },
                     texture: player_animation.texture.clone(),
                     atlas: TextureAtlas {
                         layout: player_animation.layout.clone(),
@@ -80,75 +159,249 @@ pub fn spawn_player_system(
 
 
 
-pub fn restart_level_on_input_system(
+pub fn respawn_on_input_system(
+    player_query: Query<&PlayerSlot, (With<Player>, With<IsDead>)>,
+    player_input_query: Query<(&PlayerSlot, &ActionState<PlayerAction>)>,
+    lives: Res<Lives>,
+    mut respawn_player_event: EventWriter<RespawnPlayerEvent>,
+) {
+    if lives.remaining == 0 { return; }
+    for dead_slot in player_query.iter() {
+        let Some((_, input)) = player_input_query.iter().find(|(slot, _)| *slot == dead_slot) else { continue };
+        if input.just_pressed(&PlayerAction::Respawn) {
+            respawn_player_event.send(RespawnPlayerEvent { slot: *dead_slot });
+        }
+    }
+}
+
+/// Brings dead players back at the start point while someone else is still alive,
+/// otherwise the whole level is restarted.
+pub fn respawn_player_system(
     mut commands: Commands,
-    player_query: Query<(), (With<Player>, Without<IsDead>)>,
-    player_input_query: Query<&ActionState<PlayerAction>>,
-    levels: Query<Entity, With<LevelIid>>,
+    mut respawn_player_event: EventReader<RespawnPlayerEvent>,
+    alive_query: Query<(), (With<Player>, Without<IsDead>)>,
+    start_point_query: Query<(Entity, &GlobalTransform), With<PlayerStartPoint>>,
+    current_level: Res<CurrentLevel>,
+    parent_query: Query<&Parent>,
+    level_query: Query<(Entity, &LevelIid)>,
+    level_iid_query: Query<&LevelIid>,
+    active_checkpoint: Res<ActiveCheckpoint>,
+    mut spawn_player_event: EventWriter<SpawnPlayerEvent>,
 ) {
-    if !player_query.is_empty() {return};
-    let Ok(input) = player_input_query.get_single() else {return};
-    if input.just_pressed(&PlayerAction::Respawn) {
-        commands.entity(levels.single()).insert(Respawn);
+    for respawn_event in respawn_player_event.read() {
+        // A reached checkpoint keeps the level as it is
+        if let Some(checkpoint) = &active_checkpoint.0 {
+            spawn_player_event.send(SpawnPlayerEvent {
+                translation: checkpoint.translation,
+                slot: respawn_event.slot,
+                keep_inventory: true,
+            });
+            continue;
+        }
+        if alive_query.is_empty() {
+            let Some((level, _)) = level_query.iter()
+                .find(|(_, iid)| current_level.0.as_ref() == Some(&iid.to_string())) else { return };
+            commands.entity(level).insert(Respawn);
+            return;
+        }
+        let Some((_, start_point)) = start_point_query.iter()
+            .find(|(entity, _)| current_level.contains(*entity, &parent_query, &level_iid_query)) else { continue };
+        spawn_player_event.send(SpawnPlayerEvent {
+            translation: start_point.translation(),
+            slot: respawn_event.slot,
+            keep_inventory: true,
+        });
     }
 }
 
 pub fn spawn_player_at_start_system(
     helper: TransformHelper,
     start_point_query: Query<Entity, Added<PlayerStartPoint>>,
+    input_query: Query<&PlayerSlot, With<Joined>>,
+    current_level: Res<CurrentLevel>,
+    parent_query: Query<&Parent>,
+    level_query: Query<&LevelIid>,
+    level_transition: Option<Res<LevelTransition>>,
+    active_checkpoint: Res<ActiveCheckpoint>,
     mut spawn_player_event: EventWriter<SpawnPlayerEvent>,
 ) {
-    let Ok(start_point_entity) = start_point_query.get_single() else { return };
+    // level transitions place the players themselves
+    if level_transition.is_some() { return; }
+    // neighbouring levels are loaded as well, only their selected level counts
+    let Some(start_point_entity) = start_point_query.iter()
+        .find(|entity| current_level.contains(*entity, &parent_query, &level_query)) else { return };
     // at start the global transform is not propagated yet
     let Ok(start_point) = helper.compute_global_transform(start_point_entity) else { return };
-    spawn_player_event.send(SpawnPlayerEvent {
-        translation: start_point.translation(),
-    });
+    // a checkpoint restored from a save game takes precedence
+    let translation = active_checkpoint.0.as_ref()
+        .map_or(start_point.translation(), |checkpoint| checkpoint.translation);
+    for slot in input_query.iter() {
+        spawn_player_event.send(SpawnPlayerEvent {
+            translation,
+            slot: *slot,
+            keep_inventory: false,
+        });
+    }
 }
 
 
 pub fn player_animation_system(
-    player_query: Query<Has<Grounded>, With<Player>>,
+    player_query: Query<(&MovementIntent, Has<Grounded>, Option<&TouchingWall>, Has<WallSliding>, Has<IsDead>, &Children), With<Player>>,
     player_animations: Res<PlayerAnimations>,
-    input_query: Query<&ActionState<PlayerAction>>,
     mut player_visual_query: Query<(&mut Sprite, &mut SpritesheetAnimation), With<PlayerVisual>>,
 ) {
-    let Ok(input) = input_query.get_single() else {return};
-    let Ok(grounded) = player_query.get_single() else {return};
-
-    let Ok((mut sprite, mut animation)) = player_visual_query.get_single_mut() else {return};
-    let move_direction = input.clamped_axis_pair(&PlayerAction::Move).unwrap().x();
-    if move_direction < 0.0 {
-        sprite.flip_x = true
-    } else if move_direction > 0.0 {
-        sprite.flip_x = false
+    for (intent, grounded, touching_wall, wall_sliding, is_dead, children) in player_query.iter() {
+        let Some(visual) = children.iter().find(|child| player_visual_query.contains(**child)) else { continue };
+        let Ok((mut sprite, mut animation)) = player_visual_query.get_mut(*visual) else { continue };
+
+        if is_dead {
+            if animation.animation_id != player_animations.death_animation {
+                animation.animation_id = player_animations.death_animation;
+            }
+            continue;
+        }
+
+        let move_direction = intent.direction;
+        if move_direction < 0.0 {
+            sprite.flip_x = true
+        } else if move_direction > 0.0 {
+            sprite.flip_x = false
+        }
+
+        if wall_sliding {
+            // Face away from the wall while sliding down
+            if let Some(wall) = touching_wall {
+                sprite.flip_x = wall.direction > 0.0;
+            }
+            animation.animation_id = player_animations.wall_slide_animation;
+        } else if grounded {
+            if move_direction.abs() <= 0.01 {
+                animation.animation_id = player_animations.idle_animation;
+            } else {
+                animation.animation_id = player_animations.run_animation;
+            }
+        } else {
+            animation.animation_id = player_animations.jump_animation;
+        }
     }
+}
+
+/// Applies [`DamageEvent`]s to players, killing them once they run out of health.
+pub fn player_damage_system(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut player_query: Query<
+        (&mut Health, &Knockback, &mut LinearVelocity, &mut CollisionLayers, &Children, Has<Invulnerable>),
+        (With<Player>, Without<IsDead>),
+    >,
+    mut player_visual_query: Query<&mut Visibility, With<PlayerVisual>>,
+    death_settings: Res<DeathSettings>,
+    mut lives: ResMut<Lives>,
+    mut player_hurt_event: EventWriter<PlayerHurt>,
+    mut damaged: Local<Vec<Entity>>,
+) {
+    damaged.clear();
+    for damage in damage_events.read() {
+        // Only the first hit per frame counts, invulnerability is only inserted once commands are applied
+        if damaged.contains(&damage.target) { continue; }
+        let Ok((mut health, knockback, mut linear_velocity, mut collision_layers, children, is_invulnerable)) =
+            player_query.get_mut(damage.target) else { continue };
+        if is_invulnerable && !damage.instant_kill { continue; }
+        damaged.push(damage.target);
 
-    if grounded {
-        if move_direction.abs() <= 0.01 {
-            animation.animation_id = player_animations.idle_animation;
+        health.current = if damage.instant_kill { 0 } else { health.current.saturating_sub(damage.amount) };
+        player_hurt_event.send(PlayerHurt);
+
+        if health.current == 0 {
+            collision_layers.memberships = LayerMask::from(GamePhysicsLayer::Dead);
+            lives.remaining = lives.remaining.saturating_sub(1);
+            // The invulnerability flashing may have hidden the player, and it won't get to show it again
+            for child in children.iter() {
+                let Ok(mut visibility) = player_visual_query.get_mut(*child) else { continue };
+                *visibility = Visibility::Inherited;
+            }
+            commands.entity(damage.target)
+                .remove::<Invulnerable>()
+                .insert((
+                    IsDead,
+                    DeathTimer(Timer::from_seconds(death_settings.respawn_delay, TimerMode::Once)),
+                ));
         } else {
-            animation.animation_id = player_animations.run_animation;
+            linear_velocity.0 = damage.knockback * knockback.0;
+            commands.entity(damage.target)
+                .insert(Invulnerable(Timer::from_seconds(1.0, TimerMode::Once)));
+        }
+    }
+}
+
+/// Slows down time while a player dies and respawns them once the death sequence is over.
+pub fn death_sequence_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    death_settings: Res<DeathSettings>,
+    lives: Res<Lives>,
+    mut dead_query: Query<(Entity, &PlayerSlot, &mut DeathTimer), With<IsDead>>,
+    mut respawn_player_event: EventWriter<RespawnPlayerEvent>,
+) {
+    let mut is_dying = false;
+    for (entity, slot, mut death_timer) in dead_query.iter_mut() {
+        // Game time, so slow motion and hit-stop hold the sequence back as much as the death animation
+        death_timer.0.tick(time.delta());
+        if !death_timer.0.finished() {
+            is_dying = true;
+            continue;
+        }
+
+        commands.entity(entity).remove::<DeathTimer>();
+        if death_settings.auto_respawn && lives.remaining > 0 {
+            respawn_player_event.send(RespawnPlayerEvent { slot: *slot });
+        }
+    }
+
+    let relative_speed = if is_dying { death_settings.slow_motion_speed } else { 1.0 };
+    if virtual_time.relative_speed() != relative_speed {
+        virtual_time.set_relative_speed(relative_speed);
+    }
+}
+
+/// Counts down invulnerability and flashes the player sprite meanwhile.
+pub fn invulnerability_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut player_query: Query<(Entity, &mut Invulnerable, &Children), With<Player>>,
+    mut player_visual_query: Query<&mut Visibility, With<PlayerVisual>>,
+) {
+    for (entity, mut invulnerable, children) in player_query.iter_mut() {
+        invulnerable.0.tick(time.delta());
+        let finished = invulnerable.0.finished();
+        if finished {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+
+        let flash_visible = finished || (invulnerable.0.elapsed_secs() * 10.0) as u32 % 2 == 0;
+        for child in children.iter() {
+            let Ok(mut visibility) = player_visual_query.get_mut(*child) else { continue };
+            *visibility = if flash_visible { Visibility::Inherited } else { Visibility::Hidden };
         }
-    } else {
-        animation.animation_id = player_animations.jump_animation;
     }
 }
 
 pub fn coin_collection_system(
     mut commands: Commands,
-    coin_query: Query<Entity, With<Coin>>,
+    coin_query: Query<(&GlobalTransform, Has<SpatialSound>), With<Coin>>,
     mut player_query: Query<(&mut Inventory, &CollidingEntities), (With<Player>, Without<IsDead>)>,
     mut coin_collected_event: EventWriter<CoinCollected>,
 ) {
     for (mut inventory, collisions) in player_query.iter_mut() {
         for other in collisions.iter() {
-            if coin_query.contains(*other) {
+            if let Ok((coin_transform, is_spatial)) = coin_query.get(*other) {
                 commands.entity(*other).despawn_recursive();
                 inventory.collected_coins += 1;
                 coin_collected_event.send(CoinCollected {
                     amount_collected: 1,
                     total_collected: inventory.collected_coins,
+                    position: is_spatial.then(|| coin_transform.translation()),
                 });
             }
         }
@@ -0,0 +1,189 @@
+use std::collections::{BTreeMap, BTreeSet};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::{LevelIid, LevelSelection, Respawn};
+use serde::{Deserialize, Serialize};
+use crate::{Inventory, Player};
+use crate::camera::components::AccessibilitySettings;
+use crate::hud::components::LevelStats;
+use crate::player::components::PlayerSlot;
+use crate::save::storage;
+use crate::world::components::{ActiveCheckpoint, AudioSettings, CarriedInventories, CheckpointLocation, CurrentLevel};
+
+/// The best run of a single level.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct LevelRecord {
+    /// Seconds of the fastest completion
+    pub best_time: Option<f32>,
+    pub max_coins: i32,
+}
+
+/// Best runs by LDtk level iid, persisted across sessions.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct LevelRecords {
+    pub levels: BTreeMap<String, LevelRecord>,
+}
+
+impl LevelRecords {
+    pub const FILE_NAME: &'static str = "records.ron";
+
+    /// Updates the record of a level with a finished run.
+    pub fn record(&mut self, level_iid: &str, stats: &LevelStats) -> LevelResult {
+        let record = self.levels.entry(level_iid.to_owned()).or_default();
+        let new_best_time = record.best_time.map_or(true, |best_time| stats.elapsed < best_time);
+        let new_max_coins = stats.coins_collected > record.max_coins;
+
+        if new_best_time {
+            record.best_time = Some(stats.elapsed);
+        }
+        if new_max_coins {
+            record.max_coins = stats.coins_collected;
+        }
+
+        LevelResult {
+            stats: stats.clone(),
+            record: record.clone(),
+            new_best_time,
+            new_max_coins,
+        }
+    }
+}
+
+/// The last finished level, shown on the results screen.
+#[derive(Resource, Clone, Debug)]
+pub struct LevelResult {
+    pub stats: LevelStats,
+    /// The record including this run
+    pub record: LevelRecord,
+    pub new_best_time: bool,
+    pub new_max_coins: bool,
+}
+
+/// Settings the player chose, stored with the save game.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct UserSettings {
+    pub auto_respawn: bool,
+    pub audio: AudioSettings,
+    pub accessibility: AccessibilitySettings,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        UserSettings {
+            auto_respawn: true,
+            audio: AudioSettings::default(),
+            accessibility: AccessibilitySettings::default(),
+        }
+    }
+}
+
+/// Iids of the levels the players reached.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct UnlockedLevels(pub BTreeSet<String>);
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct SavedCheckpoint {
+    pub iid: String,
+    pub translation: [f32; 3],
+}
+
+/// The progress of a run, written whenever the players make progress.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct SaveGame {
+    /// The [`SaveGame::VERSION`] the file was written with, `0` for files from before versioning
+    pub version: u32,
+    pub unlocked_levels: BTreeSet<String>,
+    /// The iid of the level the players were in
+    pub current_level: Option<String>,
+    pub checkpoint: Option<SavedCheckpoint>,
+    /// Inventories by player slot
+    pub inventories: BTreeMap<usize, Inventory>,
+    pub settings: UserSettings,
+}
+
+impl SaveGame {
+    pub const FILE_NAME: &'static str = "savegame.ron";
+    pub const VERSION: u32 = 1;
+
+    /// Loads the save game, `None` if there is none or it can't be used.
+    pub fn load() -> Option<Self> {
+        let save_game: SaveGame = storage::try_load(Self::FILE_NAME)?;
+        save_game.migrate()
+    }
+
+    /// Brings older save games up to date, `None` for saves of a newer game.
+    /// Fields added within a version are filled in by their defaults.
+    fn migrate(mut self) -> Option<Self> {
+        if self.version > Self::VERSION {
+            warn!("Ignoring save game with unsupported version {}", self.version);
+            return None;
+        }
+        if self.version < 1 {
+            // Saves from before versioning didn't unlock levels, the current one was reached for sure
+            self.unlocked_levels.extend(self.current_level.clone());
+            self.version = 1;
+        }
+        Some(self)
+    }
+
+    pub fn capture(world: &mut World) -> Self {
+        // Players travelling between levels only exist as carried inventories
+        let mut inventories: BTreeMap<usize, Inventory> = world.get_resource::<CarriedInventories>()
+            .map(|carried| carried.0.iter().map(|(slot, inventory)| (slot.0, inventory.clone())).collect())
+            .unwrap_or_default();
+        let mut player_query = world.query_filtered::<(&PlayerSlot, &Inventory), With<Player>>();
+        for (slot, inventory) in player_query.iter(world) {
+            inventories.insert(slot.0, inventory.clone());
+        }
+
+        SaveGame {
+            version: Self::VERSION,
+            unlocked_levels: world.get_resource::<UnlockedLevels>()
+                .map(|unlocked| unlocked.0.clone())
+                .unwrap_or_default(),
+            current_level: world.get_resource::<CurrentLevel>().and_then(|current| current.0.clone()),
+            checkpoint: world.get_resource::<ActiveCheckpoint>()
+                .and_then(|active| active.0.as_ref())
+                .map(|checkpoint| SavedCheckpoint {
+                    iid: checkpoint.iid.clone(),
+                    translation: checkpoint.translation.to_array(),
+                }),
+            inventories,
+            settings: world.get_resource::<UserSettings>().cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Restores the progress, the players respawn once the saved level (re)spawned.
+    pub fn apply(self, world: &mut World) {
+        let mut player_query = world.query_filtered::<Entity, With<Player>>();
+        let players: Vec<Entity> = player_query.iter(world).collect();
+        for player in players {
+            despawn_with_children_recursive(world, player);
+        }
+
+        world.insert_resource(UnlockedLevels(self.unlocked_levels));
+        world.insert_resource(ActiveCheckpoint(self.checkpoint.map(|checkpoint| CheckpointLocation {
+            iid: checkpoint.iid,
+            translation: Vec3::from_array(checkpoint.translation),
+        })));
+        world.insert_resource(CarriedInventories(self.inventories.into_iter()
+            .map(|(slot, inventory)| (PlayerSlot(slot), inventory))
+            .collect()));
+        world.insert_resource(self.settings);
+
+        let Some(level_iid) = self.current_level else { return };
+        // An already spawned level has to respawn to place the players again
+        let mut level_query = world.query::<(Entity, &LevelIid)>();
+        let spawned_level = level_query.iter(world)
+            .find(|(_, iid)| iid.to_string() == level_iid)
+            .map(|(entity, _)| entity);
+        if let Some(level) = spawned_level {
+            world.entity_mut(level).insert(Respawn);
+        }
+        world.insert_resource(LevelSelection::iid(level_iid));
+    }
+}
@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+use crate::game_state::components::GameState;
+use crate::save::components::*;
+use crate::save::systems::*;
+use crate::world::components::{ActiveCheckpoint, CurrentLevel};
+use crate::world::systems::{level_exit_system, update_current_level_system};
+
+pub mod components;
+pub mod storage;
+mod systems;
+#[cfg(test)]
+mod tests;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(storage::load::<LevelRecords>(LevelRecords::FILE_NAME))
+            .insert_resource(SaveGame::load().map(|save_game| save_game.settings).unwrap_or_default())
+            .init_resource::<UnlockedLevels>()
+            .add_systems(Update, apply_user_settings_system.run_if(resource_changed::<UserSettings>))
+            .add_systems(Update, unlock_level_system.after(update_current_level_system).run_if(resource_changed::<CurrentLevel>))
+            .add_systems(Update, autosave_system
+                .after(unlock_level_system)
+                .run_if(in_state(GameState::Playing))
+                .run_if(resource_changed::<CurrentLevel>.or_else(resource_changed::<ActiveCheckpoint>)))
+            .add_systems(OnEnter(GameState::Paused), autosave_system)
+            .add_systems(OnExit(GameState::Paused), autosave_system)
+            .add_systems(Update, record_level_result_system.after(level_exit_system).run_if(in_state(GameState::Playing)))
+        ;
+    }
+}
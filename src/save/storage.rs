@@ -0,0 +1,49 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Overrides the directory save files are written to, e.g. to keep tests away from real saves.
+pub const SAVE_DIR_ENV: &str = "BEVYCATION_SAVE_DIR";
+
+pub fn save_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os(SAVE_DIR_ENV) {
+        return PathBuf::from(dir);
+    }
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("bevycation")
+}
+
+/// Loads a save file, `None` if it is missing or can't be read.
+pub fn try_load<T: DeserializeOwned>(file_name: &str) -> Option<T> {
+    let path = save_dir().join(file_name);
+    let content = fs::read_to_string(&path).ok()?;
+    match ron::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(error) => {
+            warn!("Ignoring unreadable save file {}: {}", path.display(), error);
+            None
+        }
+    }
+}
+
+/// Loads a save file, falling back to the default if it is missing or can't be read.
+pub fn load<T: DeserializeOwned + Default>(file_name: &str) -> T {
+    try_load(file_name).unwrap_or_default()
+}
+
+pub fn save<T: Serialize>(file_name: &str, value: &T) -> Result<(), Box<dyn Error>> {
+    let dir = save_dir();
+    fs::create_dir_all(&dir)?;
+
+    let content = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())?;
+    // Write next to the save first, so a crash never leaves a half written file behind
+    let path = dir.join(file_name);
+    let temporary_path = path.with_extension("tmp");
+    fs::write(&temporary_path, content)?;
+    fs::rename(&temporary_path, &path)?;
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use crate::camera::components::AccessibilitySettings;
+use crate::hud::components::LevelStats;
+use crate::player::components::DeathSettings;
+use crate::save::components::*;
+use crate::save::storage;
+use crate::world::components::{AudioSettings, CurrentLevel, LevelExitReached};
+
+pub fn record_level_result_system(
+    mut commands: Commands,
+    mut level_exit_reached: EventReader<LevelExitReached>,
+    current_level: Res<CurrentLevel>,
+    level_stats: Res<LevelStats>,
+    mut level_records: ResMut<LevelRecords>,
+) {
+    if level_exit_reached.read().last().is_none() { return; }
+    let Some(level_iid) = &current_level.0 else { return };
+
+    let level_result = level_records.record(level_iid, &level_stats);
+    if let Err(error) = storage::save(LevelRecords::FILE_NAME, &*level_records) {
+        error!("Failed to save level records: {}", error);
+    }
+    commands.insert_resource(level_result);
+}
+
+pub fn apply_user_settings_system(
+    user_settings: Res<UserSettings>,
+    mut death_settings: ResMut<DeathSettings>,
+    mut audio_settings: ResMut<AudioSettings>,
+    mut accessibility_settings: ResMut<AccessibilitySettings>,
+) {
+    death_settings.auto_respawn = user_settings.auto_respawn;
+    if *audio_settings != user_settings.audio {
+        *audio_settings = user_settings.audio.clone();
+    }
+    if *accessibility_settings != user_settings.accessibility {
+        *accessibility_settings = user_settings.accessibility.clone();
+    }
+}
+
+pub fn unlock_level_system(
+    current_level: Res<CurrentLevel>,
+    mut unlocked_levels: ResMut<UnlockedLevels>,
+) {
+    let Some(level_iid) = &current_level.0 else { return };
+    if !unlocked_levels.0.contains(level_iid) {
+        unlocked_levels.0.insert(level_iid.clone());
+    }
+}
+
+pub fn autosave_system(
+    world: &mut World,
+) {
+    let save_game = SaveGame::capture(world);
+    if let Err(error) = storage::save(SaveGame::FILE_NAME, &save_game) {
+        error!("Failed to save the game: {}", error);
+    }
+}
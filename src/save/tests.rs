@@ -0,0 +1,117 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::LevelSelection;
+use crate::{Inventory, Player};
+use crate::hud::components::LevelStats;
+use crate::player::components::PlayerSlot;
+use crate::save::components::*;
+use crate::save::{storage, SavePlugin};
+use crate::world::components::{ActiveCheckpoint, CarriedInventories, CheckpointLocation, CurrentLevel};
+
+/// The save directory is set through the environment, so tests touching it take turns.
+static SAVE_DIR_LOCK: Mutex<()> = Mutex::new(());
+
+/// Points the save directory at an empty temporary directory until dropped.
+struct TemporarySaveDir {
+    path: PathBuf,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl TemporarySaveDir {
+    fn new(name: &str) -> Self {
+        let lock = SAVE_DIR_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let path = std::env::temp_dir().join(format!("bevycation-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        std::env::set_var(storage::SAVE_DIR_ENV, &path);
+        TemporarySaveDir { path, _lock: lock }
+    }
+
+    fn write(&self, file_name: &str, content: &str) {
+        fs::create_dir_all(&self.path).unwrap();
+        fs::write(self.path.join(file_name), content).unwrap();
+    }
+}
+
+impl Drop for TemporarySaveDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+        std::env::remove_var(storage::SAVE_DIR_ENV);
+    }
+}
+
+fn saving_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(SavePlugin);
+    app
+}
+
+#[test]
+fn save_game_round_trips_into_a_new_app() {
+    let _save_dir = TemporarySaveDir::new("round-trip");
+
+    let mut world = World::new();
+    world.insert_resource(UnlockedLevels(["level_0".to_owned(), "level_1".to_owned()].into()));
+    world.insert_resource(CurrentLevel(Some("level_1".to_owned())));
+    world.insert_resource(ActiveCheckpoint(Some(CheckpointLocation {
+        iid: "checkpoint".to_owned(),
+        translation: Vec3::new(24.0, 40.0, 0.0),
+    })));
+    world.insert_resource(UserSettings { auto_respawn: false, ..default() });
+    world.spawn((Player, PlayerSlot(0), Inventory { collected_coins: 7 }));
+    world.insert_resource(CarriedInventories([(PlayerSlot(1), Inventory { collected_coins: 3 })].into_iter().collect()));
+    storage::save(SaveGame::FILE_NAME, &SaveGame::capture(&mut world)).unwrap();
+
+    let mut level_records = LevelRecords::default();
+    level_records.record("level_0", &LevelStats { coins_collected: 5, coins_total: 8, elapsed: 42.5, deaths: 2 });
+    storage::save(LevelRecords::FILE_NAME, &level_records).unwrap();
+
+    let mut app = saving_app();
+    assert_eq!(*app.world.resource::<LevelRecords>(), level_records);
+    assert!(!app.world.resource::<UserSettings>().auto_respawn);
+
+    SaveGame::load().expect("the save game should load").apply(&mut app.world);
+    assert_eq!(app.world.resource::<UnlockedLevels>().0, ["level_0".to_owned(), "level_1".to_owned()].into());
+    assert_eq!(*app.world.resource::<LevelSelection>(), LevelSelection::iid("level_1"));
+    assert_eq!(app.world.resource::<ActiveCheckpoint>().0, Some(CheckpointLocation {
+        iid: "checkpoint".to_owned(),
+        translation: Vec3::new(24.0, 40.0, 0.0),
+    }));
+    let carried = &app.world.resource::<CarriedInventories>().0;
+    assert_eq!(carried[&PlayerSlot(0)].collected_coins, 7);
+    assert_eq!(carried[&PlayerSlot(1)].collected_coins, 3);
+}
+
+#[test]
+fn corrupted_save_game_is_ignored() {
+    let save_dir = TemporarySaveDir::new("corrupted");
+    save_dir.write(SaveGame::FILE_NAME, "(version: 1, unlocked_levels: [\"level_0\"");
+    save_dir.write(LevelRecords::FILE_NAME, "not a record");
+
+    assert!(SaveGame::load().is_none());
+    let app = saving_app();
+    assert_eq!(*app.world.resource::<UserSettings>(), UserSettings::default());
+    assert_eq!(*app.world.resource::<LevelRecords>(), LevelRecords::default());
+}
+
+#[test]
+fn older_save_game_is_migrated() {
+    let save_dir = TemporarySaveDir::new("older-version");
+    // Saves from before versioning have no version and no unlocked levels
+    save_dir.write(SaveGame::FILE_NAME, "(current_level: Some(\"level_1\"), inventories: {0: (collected_coins: 4)})");
+
+    let save_game = SaveGame::load().expect("the save game should be migrated");
+    assert_eq!(save_game.version, SaveGame::VERSION);
+    assert_eq!(save_game.unlocked_levels, ["level_1".to_owned()].into());
+    assert_eq!(save_game.current_level.as_deref(), Some("level_1"));
+    assert_eq!(save_game.inventories[&0].collected_coins, 4);
+    assert_eq!(save_game.settings, UserSettings::default());
+}
+
+#[test]
+fn newer_save_game_is_ignored() {
+    let save_dir = TemporarySaveDir::new("newer-version");
+    save_dir.write(SaveGame::FILE_NAME, &format!("(version: {}, current_level: Some(\"level_0\"))", SaveGame::VERSION + 1));
+    assert!(SaveGame::load().is_none());
+}
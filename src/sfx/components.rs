@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+use crate::world::components::GameSounds;
+
+/// A sound effect, also put on the entity playing it to limit how many play at once.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Component)]
+pub enum SoundEffect {
+    Jump,
+    Land,
+    Coin,
+    Hurt,
+    EnemyKilled,
+    PlatformActivated,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SoundEffectConfig {
+    /// Multiplied with the sound volume of the [`AudioSettings`](crate::world::components::AudioSettings)
+    pub volume: f32,
+    /// The playback speed varies randomly by up to this much in both directions
+    pub pitch_variation: f32,
+    /// Further requests are dropped while this many are playing
+    pub max_concurrent: usize,
+}
+
+impl SoundEffect {
+    pub fn config(&self) -> SoundEffectConfig {
+        match self {
+            SoundEffect::Jump => SoundEffectConfig { volume: 0.6, pitch_variation: 0.1, max_concurrent: 2 },
+            SoundEffect::Land => SoundEffectConfig { volume: 0.5, pitch_variation: 0.15, max_concurrent: 2 },
+            SoundEffect::Coin => SoundEffectConfig { volume: 1.0, pitch_variation: 0.05, max_concurrent: 4 },
+            SoundEffect::Hurt => SoundEffectConfig { volume: 1.0, pitch_variation: 0.05, max_concurrent: 1 },
+            SoundEffect::EnemyKilled => SoundEffectConfig { volume: 0.8, pitch_variation: 0.1, max_concurrent: 3 },
+            SoundEffect::PlatformActivated => SoundEffectConfig { volume: 0.7, pitch_variation: 0.0, max_concurrent: 1 },
+        }
+    }
+
+    pub fn source(&self, game_sounds: &GameSounds) -> Handle<AudioSource> {
+        match self {
+            SoundEffect::Jump => game_sounds.jump.clone(),
+            SoundEffect::Land => game_sounds.land.clone(),
+            SoundEffect::Coin => game_sounds.coin_collected.clone(),
+            SoundEffect::Hurt => game_sounds.player_hurt.clone(),
+            SoundEffect::EnemyKilled => game_sounds.enemy_killed.clone(),
+            SoundEffect::PlatformActivated => game_sounds.platform_activated.clone(),
+        }
+    }
+}
+
+/// Requests a sound effect, played by the [`SfxPlugin`](crate::sfx::SfxPlugin).
+#[derive(Event)]
+pub struct PlaySoundEffect {
+    pub effect: SoundEffect,
+    /// Plays the sound positionally at this world position, globally if `None`
+    pub position: Option<Vec3>,
+}
+
+/// Opts an entity into positional sound, its sounds pan and fade with the distance to the
+/// [`MainCamera`](crate::MainCamera). LDtk entities opt in with their `spatial_sound` field.
+#[derive(Component, Clone, Debug, Default)]
+pub struct SpatialSound {
+    /// Loops at the entity for as long as it exists, like the hum of a machine
+    pub ambient: Option<Handle<AudioSource>>,
+}
+
+/// The looping sound spawned for a [`SpatialSound::ambient`].
+#[derive(Component)]
+pub struct AmbientSound;
@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+use crate::sfx::components::*;
+use crate::sfx::systems::*;
+
+pub mod components;
+mod systems;
+
+/// Plays the sound effects of gameplay events.
+pub struct SfxPlugin;
+
+impl Plugin for SfxPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<PlaySoundEffect>()
+            .add_systems(PostUpdate, gameplay_sound_effects_system)
+            .add_systems(PostUpdate, play_sound_effects_system.after(gameplay_sound_effects_system))
+            .add_systems(Update, spatial_sound_from_ldtk_system)
+            .add_systems(Update, setup_ambient_sound_system.after(spatial_sound_from_ldtk_system))
+            .add_systems(Update, update_ambient_volume_system)
+        ;
+    }
+}
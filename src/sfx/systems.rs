@@ -0,0 +1,139 @@
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_ecs_ldtk::prelude::*;
+use rand::Rng;
+use crate::character_controller::components::{JumpStarted, Landed};
+use crate::enemy::components::EnemyKilled;
+use crate::player::components::{CoinCollected, PlayerHurt};
+use crate::sfx::components::*;
+use crate::world::components::{AudioSettings, GameSounds, Path, PlatformActivated};
+
+/// Turns gameplay events into sound effect requests.
+pub fn gameplay_sound_effects_system(
+    mut jump_started_events: EventReader<JumpStarted>,
+    mut landed_events: EventReader<Landed>,
+    mut coin_collected_events: EventReader<CoinCollected>,
+    mut player_hurt_events: EventReader<PlayerHurt>,
+    mut enemy_killed_events: EventReader<EnemyKilled>,
+    mut platform_activated_events: EventReader<PlatformActivated>,
+    spatial_query: Query<&GlobalTransform, With<SpatialSound>>,
+    mut play_sound_effect: EventWriter<PlaySoundEffect>,
+) {
+    let mut play = |effect: SoundEffect, position: Option<Vec3>| {
+        play_sound_effect.send(PlaySoundEffect { effect, position });
+    };
+
+    // Players have no spatial sound, they are always next to the listener so their sounds stay global
+    let spatial_position = |entity: Entity| spatial_query.get(entity).ok().map(|transform| transform.translation());
+    for jump_event in jump_started_events.read() {
+        play(SoundEffect::Jump, spatial_position(jump_event.entity));
+    }
+    for landed_event in landed_events.read() {
+        play(SoundEffect::Land, spatial_position(landed_event.entity));
+    }
+    for _ in player_hurt_events.read() {
+        play(SoundEffect::Hurt, None);
+    }
+    for coin_event in coin_collected_events.read() {
+        play(SoundEffect::Coin, coin_event.position);
+    }
+    for enemy_event in enemy_killed_events.read() {
+        let position = spatial_query.contains(enemy_event.enemy).then_some(enemy_event.translation);
+        play(SoundEffect::EnemyKilled, position);
+    }
+    for platform_event in platform_activated_events.read() {
+        play(SoundEffect::PlatformActivated, spatial_position(platform_event.platform));
+    }
+}
+
+pub fn play_sound_effects_system(
+    mut commands: Commands,
+    mut play_sound_effect: EventReader<PlaySoundEffect>,
+    playing_query: Query<&SoundEffect>,
+    game_sounds: Res<GameSounds>,
+    audio_settings: Res<AudioSettings>,
+    mut playing: Local<HashMap<SoundEffect, usize>>,
+) {
+    if play_sound_effect.is_empty() { return; }
+
+    playing.clear();
+    for effect in playing_query.iter() {
+        *playing.entry(*effect).or_default() += 1;
+    }
+
+    let mut rng = rand::thread_rng();
+    for request in play_sound_effect.read() {
+        let config = request.effect.config();
+        let playing_count = playing.entry(request.effect).or_default();
+        if *playing_count >= config.max_concurrent { continue; }
+        *playing_count += 1;
+
+        let speed = 1.0 + rng.gen_range(-config.pitch_variation..=config.pitch_variation);
+        let mut sound = commands.spawn((
+            Name::new(format!("{:?} Sound", request.effect)),
+            request.effect,
+            AudioBundle {
+                source: request.effect.source(&game_sounds),
+                settings: PlaybackSettings::DESPAWN
+                    .with_volume(Volume::new(audio_settings.effective_sfx_volume() * config.volume))
+                    .with_speed(speed)
+                    .with_spatial(request.position.is_some()),
+            },
+        ));
+        if let Some(position) = request.position {
+            sound.insert(TransformBundle::from_transform(Transform::from_translation(position)));
+        }
+    }
+}
+
+/// Gives LDtk entities with their `spatial_sound` field set a [`SpatialSound`], moving platforms also hum.
+pub fn spatial_sound_from_ldtk_system(
+    mut commands: Commands,
+    entity_query: Query<(Entity, &EntityInstance, Option<&Path>), Added<EntityInstance>>,
+    game_sounds: Res<GameSounds>,
+) {
+    for (entity, entity_instance, path) in entity_query.iter() {
+        if !entity_instance.get_bool_field("spatial_sound").is_ok_and(|spatial_sound| *spatial_sound) { continue; }
+        let is_moving = path.is_some_and(|path| path.points.len() > 1);
+        commands.entity(entity).insert(SpatialSound {
+            ambient: is_moving.then(|| game_sounds.platform_hum.clone()),
+        });
+    }
+}
+
+pub fn setup_ambient_sound_system(
+    mut commands: Commands,
+    spatial_sound_query: Query<(Entity, &SpatialSound), Added<SpatialSound>>,
+    audio_settings: Res<AudioSettings>,
+) {
+    for (entity, spatial_sound) in spatial_sound_query.iter() {
+        let Some(ambient) = &spatial_sound.ambient else { continue };
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                Name::new("Ambient Sound"),
+                AmbientSound,
+                TransformBundle::default(),
+                AudioBundle {
+                    source: ambient.clone(),
+                    settings: PlaybackSettings::LOOP
+                        .with_volume(Volume::new(audio_settings.effective_sfx_volume()))
+                        .with_spatial(true),
+                },
+            ));
+        });
+    }
+}
+
+/// Follows volume changes while ambient sounds are looping.
+pub fn update_ambient_volume_system(
+    audio_settings: Res<AudioSettings>,
+    ambient_query: Query<&SpatialAudioSink, With<AmbientSound>>,
+) {
+    let volume = audio_settings.effective_sfx_volume();
+    for sink in ambient_query.iter() {
+        if sink.volume() != volume {
+            sink.set_volume(volume);
+        }
+    }
+}
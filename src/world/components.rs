@@ -1,9 +1,13 @@
+use bevy::audio::Volume;
 use bevy::prelude::*;
-use bevy::utils::HashSet;
+use bevy::utils::{HashMap, HashSet};
 use bevy_ecs_ldtk::prelude::*;
 use bevy_ecs_ldtk::utils::{ldtk_pixel_coords_to_translation_pivoted};
 use bevy_spritesheet_animation::prelude::*;
 use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::Inventory;
+use crate::player::components::PlayerSlot;
 
 
 #[derive(Resource)]
@@ -27,11 +31,55 @@ impl FromWorld for GameFonts {
     }
 }
 
+/// Volumes applied to every sound spawned from [`GameSounds`].
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master_volume: 0.2,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn effective_music_volume(&self) -> f32 {
+        if self.muted { 0.0 } else { self.master_volume * self.music_volume }
+    }
+
+    pub fn effective_sfx_volume(&self) -> f32 {
+        if self.muted { 0.0 } else { self.master_volume * self.sfx_volume }
+    }
+
+    pub fn music_playback(&self) -> PlaybackSettings {
+        PlaybackSettings::LOOP.with_volume(Volume::new(self.effective_music_volume()))
+    }
+}
+
+#[derive(Component)]
+pub struct BackgroundMusic;
+
 #[derive(Resource)]
 pub struct GameSounds {
     pub background_music: Handle<AudioSource>,
     pub coin_collected: Handle<AudioSource>,
     pub player_hurt: Handle<AudioSource>,
+    pub enemy_killed: Handle<AudioSource>,
+    pub jump: Handle<AudioSource>,
+    pub land: Handle<AudioSource>,
+    pub platform_activated: Handle<AudioSource>,
+    /// Loops at moving platforms with a [`SpatialSound`](crate::sfx::components::SpatialSound)
+    pub platform_hum: Handle<AudioSource>,
 }
 
 impl FromWorld for GameSounds {
@@ -40,10 +88,20 @@ impl FromWorld for GameSounds {
         let background_music = asset_server.load("music/time_for_adventure.mp3");
         let coin_collected = asset_server.load("sounds/coin.wav");
         let player_hurt = asset_server.load("sounds/hurt.wav");
+        let enemy_killed = asset_server.load("sounds/explosion.wav");
+        let jump = asset_server.load("sounds/jump.wav");
+        let land = asset_server.load("sounds/tap.wav");
+        let platform_activated = asset_server.load("sounds/power_up.wav");
+        let platform_hum = asset_server.load("sounds/platform_hum.wav");
         GameSounds {
             background_music,
             coin_collected,
             player_hurt,
+            enemy_killed,
+            jump,
+            land,
+            platform_activated,
+            platform_hum,
         }
     }
 }
@@ -56,6 +114,7 @@ pub enum GamePhysicsLayer {
     Collectible,
     KillZone,
     Dead,
+    Trigger,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
@@ -87,6 +146,135 @@ pub struct PlatformBundle {
     platform: Platform,
     #[ldtk_entity]
     path: Path,
+    #[with(PlatformTrigger::from_field)]
+    trigger: PlatformTrigger,
+    #[sprite_sheet_bundle(no_grid)]
+    sprite_sheet_bundle: SpriteSheetBundle,
+}
+
+/// When a platform starts following its path.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Reflect)]
+pub enum PlatformActivation {
+    /// Moves from the moment it is spawned
+    #[default]
+    Always,
+    /// Waits until a player stands on it
+    OnStand,
+    /// Waits until its linked [`Switch`] is pressed
+    OnSwitch,
+}
+
+impl PlatformActivation {
+    fn from_ldtk_enum(value: &str) -> Option<Self> {
+        match value {
+            "Always" => Some(PlatformActivation::Always),
+            "OnStand" => Some(PlatformActivation::OnStand),
+            "OnSwitch" => Some(PlatformActivation::OnSwitch),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub struct PlatformTrigger {
+    pub activation: PlatformActivation,
+    /// The iid of the switch entity that activates the platform
+    pub switch_iid: Option<String>,
+    /// Seconds without a player on the platform before it returns to its start, stays put if `None`
+    pub return_delay: Option<f32>,
+    /// Seconds since a player last stood on the platform
+    pub idle_time: f32,
+}
+
+impl PlatformTrigger {
+    fn from_field(entity_instance: &EntityInstance) -> Self {
+        PlatformTrigger {
+            activation: ldtk_enum_field(entity_instance, "activation", PlatformActivation::from_ldtk_enum),
+            switch_iid: entity_instance.get_entity_ref_field("switch")
+                .ok()
+                .map(|switch| switch.entity_iid.clone()),
+            return_delay: entity_instance.get_float_field("return_delay").ok().copied(),
+            idle_time: 0.0,
+        }
+    }
+}
+
+/// A marker component for platforms that are following their path.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+#[component(storage = "SparseSet")]
+pub struct PlatformActive;
+
+/// A marker component for platforms travelling back to their first point before they stop.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+#[component(storage = "SparseSet")]
+pub struct ReturningToStart;
+
+#[derive(Event)]
+pub struct PlatformActivated {
+    pub platform: Entity,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Switch {
+    pub pressed: bool,
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct SwitchBundle {
+    switch: Switch,
+    #[sprite_sheet_bundle(no_grid)]
+    sprite_sheet_bundle: SpriteSheetBundle,
+}
+
+/// Sent when a player steps on a [`Switch`], identified by its LDtk iid.
+#[derive(Event)]
+pub struct SwitchActivated {
+    pub iid: String,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum CrumbleState {
+    #[default]
+    Intact,
+    Shaking,
+    Falling,
+}
+
+/// A platform that shakes and falls once a player stood on it for a while, and respawns later.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub struct CrumblingPlatform {
+    /// Seconds a player can stand on the platform before it starts shaking
+    pub crumble_delay: f32,
+    /// Seconds the platform shakes before it falls
+    pub shake_duration: f32,
+    /// Seconds after falling before the platform is back in place
+    pub respawn_delay: f32,
+    pub state: CrumbleState,
+    /// Time spent in the current state
+    pub timer: f32,
+    /// Where the platform was placed in the level
+    pub origin: Option<Vec3>,
+}
+
+impl CrumblingPlatform {
+    fn from_field(entity_instance: &EntityInstance) -> Self {
+        let float_field = |identifier: &str, default: f32| {
+            entity_instance.get_float_field(identifier).copied().unwrap_or(default)
+        };
+        CrumblingPlatform {
+            crumble_delay: float_field("crumble_delay", 0.5),
+            shake_duration: float_field("shake_duration", 0.4),
+            respawn_delay: float_field("respawn_delay", 3.0),
+            ..default()
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct CrumblingPlatformBundle {
+    platform: Platform,
+    #[with(CrumblingPlatform::from_field)]
+    crumbling_platform: CrumblingPlatform,
     #[sprite_sheet_bundle(no_grid)]
     sprite_sheet_bundle: SpriteSheetBundle,
 }
@@ -106,12 +294,148 @@ pub enum PassThroughOneWayPlatform {
 }
 
 
+/// How a [`Path`] continues once its last point is reached.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Reflect)]
+pub enum PathMode {
+    /// Travels back and forth between the first and the last point
+    #[default]
+    PingPong,
+    /// Returns from the last point to the first one and starts over
+    Loop,
+    /// Stops at the last point
+    OneShot,
+}
+
+impl PathMode {
+    fn from_ldtk_enum(value: &str) -> Option<Self> {
+        match value {
+            "PingPong" => Some(PathMode::PingPong),
+            "Loop" => Some(PathMode::Loop),
+            "OneShot" => Some(PathMode::OneShot),
+            _ => None,
+        }
+    }
+}
+
+/// The speed curve used while travelling between two points of a [`Path`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Reflect)]
+pub enum PathEasing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl PathEasing {
+    /// The slowest a platform moves while easing, as a fraction of its speed,
+    /// otherwise it would never leave a point.
+    const MIN_SPEED_FACTOR: f32 = 0.1;
+
+    fn from_ldtk_enum(value: &str) -> Option<Self> {
+        match value {
+            "Linear" => Some(PathEasing::Linear),
+            "EaseIn" => Some(PathEasing::EaseIn),
+            "EaseOut" => Some(PathEasing::EaseOut),
+            "EaseInOut" => Some(PathEasing::EaseInOut),
+            _ => None,
+        }
+    }
+
+    /// The speed multiplier at `progress` (0 to 1) along a segment.
+    /// Each curve averages out to 1, so a segment takes about as long as with [`PathEasing::Linear`].
+    pub fn speed_factor(&self, progress: f32) -> f32 {
+        let t = progress.clamp(0.0, 1.0);
+        let factor = match self {
+            PathEasing::Linear => return 1.0,
+            PathEasing::EaseIn => 2.0 * t,
+            PathEasing::EaseOut => 2.0 * (1.0 - t),
+            PathEasing::EaseInOut => 6.0 * t * (1.0 - t),
+        };
+        factor.max(Self::MIN_SPEED_FACTOR)
+    }
+}
+
+/// Reads an LDtk enum field, falling back to the default if it is missing or unknown.
+fn ldtk_enum_field<T: Default>(entity_instance: &EntityInstance, identifier: &str, parse: fn(&str) -> Option<T>) -> T {
+    let Ok(value) = entity_instance.get_enum_field(identifier) else { return T::default() };
+    parse(value).unwrap_or_else(|| {
+        warn!("unknown value {value} for field {identifier} of {}", entity_instance.identifier);
+        T::default()
+    })
+}
+
 #[derive(Clone, PartialEq, Debug, Default, Component)]
 pub struct Path {
     pub points: Vec<Vec2>,
+    /// The point currently travelled to
     pub index: usize,
+    /// The point currently travelled from
+    pub previous_index: usize,
     pub forward: bool,
     pub speed: f32,
+    pub mode: PathMode,
+    pub easing: PathEasing,
+    /// How long to pause at each point in seconds
+    pub wait_times: Vec<f32>,
+    /// Remaining pause at the current point
+    pub wait_timer: f32,
+    /// Set once a [`PathMode::OneShot`] path reached its end
+    pub finished: bool,
+}
+
+impl Path {
+    /// Puts the platform back at the beginning of its path.
+    pub fn reset(&mut self) {
+        self.index = 1;
+        self.previous_index = 0;
+        self.forward = true;
+        self.finished = false;
+        self.wait_timer = self.wait_time(0);
+    }
+
+    pub fn wait_time(&self, index: usize) -> f32 {
+        self.wait_times.get(index).copied().unwrap_or(0.0)
+    }
+
+    /// How far along the current segment the given position is, from 0 to 1.
+    pub fn segment_progress(&self, position: Vec2) -> f32 {
+        let from = self.points[self.previous_index];
+        let to = self.points[self.index];
+        let length = from.distance(to);
+        if length <= f32::EPSILON { return 1.0; }
+        1.0 - position.distance(to) / length
+    }
+
+    /// Moves on to the next point once the current target is reached.
+    pub fn advance(&mut self) {
+        let last = self.points.len() - 1;
+        self.previous_index = self.index;
+        match self.mode {
+            PathMode::PingPong => {
+                if self.index == last {
+                    self.forward = false;
+                } else if self.index == 0 {
+                    self.forward = true;
+                }
+                if self.forward {
+                    self.index += 1;
+                } else {
+                    self.index -= 1;
+                }
+            }
+            PathMode::Loop => {
+                self.index = (self.index + 1) % self.points.len();
+            }
+            PathMode::OneShot => {
+                if self.index == last {
+                    self.finished = true;
+                } else {
+                    self.index += 1;
+                }
+            }
+        }
+    }
 }
 
 impl LdtkEntity for Path {
@@ -151,11 +475,29 @@ impl LdtkEntity for Path {
         }
         let speed = entity_instance.get_float_field("speed")
             .expect("speed field missing");
+
+        // The optional fields below fall back to the original ping-pong behaviour
+        let mode = ldtk_enum_field(entity_instance, "mode", PathMode::from_ldtk_enum);
+        let easing = ldtk_enum_field(entity_instance, "easing", PathEasing::from_ldtk_enum);
+        // `wait_times` lists a pause per point, starting with the entity position,
+        // points without an entry use `wait_time`
+        let default_wait_time = entity_instance.get_float_field("wait_time").copied().unwrap_or(0.0);
+        let mut wait_times: Vec<f32> = entity_instance.iter_floats_field("wait_times")
+            .map(|wait_times| wait_times.copied().collect())
+            .unwrap_or_default();
+        wait_times.resize(points.len().max(wait_times.len()), default_wait_time);
+
         Path {
-            points,
             index: 1,
+            previous_index: 0,
             forward: true,
             speed: *speed,
+            mode,
+            easing,
+            wait_timer: wait_times[0],
+            wait_times,
+            finished: false,
+            points,
         }
     }
 }
@@ -218,21 +560,217 @@ pub struct PlayerStartPointBundle {
 #[derive(Event)]
 pub struct SpawnPlayerEvent {
     pub translation: Vec3,
+    pub slot: PlayerSlot,
+    /// Carry over the inventory of the slot's previous player, used when the level isn't reset
+    pub keep_inventory: bool,
 }
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct Checkpoint;
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct CheckpointBundle {
+    checkpoint: Checkpoint,
+    #[sprite_sheet_bundle(no_grid)]
+    sprite_sheet_bundle: SpriteSheetBundle,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct CheckpointLocation {
+    pub iid: String,
+    pub translation: Vec3,
+}
+
+/// The checkpoint players respawn at instead of restarting the level.
+#[derive(Resource, Clone, PartialEq, Debug, Default)]
+pub struct ActiveCheckpoint(pub Option<CheckpointLocation>);
+
+/// Sent when a player reaches a [`LevelExit`] that leads to a known level.
+#[derive(Event)]
+pub struct LevelExitReached {
+    pub target_level: String,
+}
+
+/// Sends the players to another level when touched.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct LevelExit {
+    /// The identifier of the level to go to, e.g. `Level_1`
+    pub target_level: String,
+}
+
+impl LevelExit {
+    fn from_field(entity_instance: &EntityInstance) -> Self {
+        LevelExit {
+            target_level: entity_instance.get_string_field("target_level")
+                .map(|target_level| target_level.to_owned())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct LevelExitBundle {
+    #[with(LevelExit::from_field)]
+    level_exit: LevelExit,
+    #[sprite_sheet_bundle(no_grid)]
+    sprite_sheet_bundle: SpriteSheetBundle,
+}
+
+/// Which camera axes a [`CameraZone`] pins to its center.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Reflect)]
+pub enum CameraAxisLock {
+    #[default]
+    None,
+    Horizontal,
+    Vertical,
+    Both,
+}
+
+impl CameraAxisLock {
+    fn from_ldtk_enum(value: &str) -> Option<Self> {
+        match value {
+            "None" => Some(CameraAxisLock::None),
+            "Horizontal" => Some(CameraAxisLock::Horizontal),
+            "Vertical" => Some(CameraAxisLock::Vertical),
+            "Both" => Some(CameraAxisLock::Both),
+            _ => None,
+        }
+    }
+
+    /// `1.0` for each locked axis.
+    pub fn weights(&self) -> Vec2 {
+        match self {
+            CameraAxisLock::None => Vec2::ZERO,
+            CameraAxisLock::Horizontal => Vec2::X,
+            CameraAxisLock::Vertical => Vec2::Y,
+            CameraAxisLock::Both => Vec2::ONE,
+        }
+    }
+}
+
+/// An area that changes the camera framing while the players are inside.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub struct CameraZone {
+    /// The size of the zone, centered on the entity
+    pub size: Vec2,
+    /// Overrides the zoom of the camera rig
+    pub zoom: Option<f32>,
+    pub lock_axis: CameraAxisLock,
+    /// Added to where the camera would look otherwise
+    pub offset: Vec2,
+}
+
+impl CameraZone {
+    fn from_field(entity_instance: &EntityInstance) -> Self {
+        CameraZone {
+            size: Vec2::new(entity_instance.width as f32, entity_instance.height as f32),
+            zoom: entity_instance.get_float_field("zoom").ok().copied(),
+            lock_axis: ldtk_enum_field(entity_instance, "lock_axis", CameraAxisLock::from_ldtk_enum),
+            offset: Vec2::new(
+                entity_instance.get_float_field("offset_x").copied().unwrap_or(0.0),
+                entity_instance.get_float_field("offset_y").copied().unwrap_or(0.0),
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct CameraZoneBundle {
+    #[with(CameraZone::from_field)]
+    camera_zone: CameraZone,
+}
+
+/// The pixel size of a spawned LDtk level.
+#[derive(Copy, Clone, PartialEq, Debug, Component)]
+pub struct LevelBounds {
+    pub size: Vec2,
+}
+
+impl LevelBounds {
+    /// The area covered by the level in world space, levels are anchored at their bottom left corner.
+    pub fn rect(&self, transform: &GlobalTransform) -> Rect {
+        let min = transform.translation().truncate();
+        Rect::from_corners(min, min + self.size)
+    }
+}
+
+/// The iid of the selected level, the one players are playing in.
+#[derive(Resource, Clone, PartialEq, Debug, Default)]
+pub struct CurrentLevel(pub Option<String>);
+
+impl CurrentLevel {
+    /// Whether `entity` was spawned as part of the current level.
+    pub fn contains(&self, entity: Entity, parent_query: &Query<&Parent>, level_query: &Query<&LevelIid>) -> bool {
+        let Some(current_iid) = &self.0 else { return false };
+        parent_query.iter_ancestors(entity)
+            .any(|ancestor| level_query.get(ancestor).is_ok_and(|iid| iid.to_string() == *current_iid))
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LevelTransitionPhase {
+    FadeOut,
+    /// Waiting for the target level to spawn its start point
+    Loading,
+    FadeIn,
+}
+
+/// A running transition to another level, started by a [`LevelExit`].
+#[derive(Resource, Clone, Debug)]
+pub struct LevelTransition {
+    pub target_level: String,
+    pub phase: LevelTransitionPhase,
+    pub timer: Timer,
+}
+
+impl LevelTransition {
+    pub const FADE_DURATION: f32 = 0.5;
+
+    pub fn new(target_level: String) -> Self {
+        LevelTransition {
+            target_level,
+            phase: LevelTransitionPhase::FadeOut,
+            timer: Timer::from_seconds(Self::FADE_DURATION, TimerMode::Once),
+        }
+    }
+}
+
+/// Inventories the players entered the current level with, restored when the level restarts.
+#[derive(Resource, Clone, Default)]
+pub struct CarriedInventories(pub HashMap<PlayerSlot, Inventory>);
+
+/// The full screen overlay used to fade between levels.
+#[derive(Component)]
+pub struct ScreenFade;
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
 pub struct KillZone;
 
+/// Whether touching a [`KillZone`] kills regardless of the remaining health, as for pits.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct InstantKill(pub bool);
+
+/// Sent when something hurts an entity, the receiver decides how much it is affected.
+#[derive(Event)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: u32,
+    /// The direction the target is pushed towards
+    pub knockback: Vec2,
+    pub instant_kill: bool,
+}
+
 #[derive(Clone, Default, Bundle)]
 pub struct KillZoneBundle {
     pub kill_zone: KillZone,
+    instant_kill: InstantKill,
     rigid_body: RigidBody,
     collider: Collider,
     sensor: Sensor,
 }
 
 impl LdtkEntity for KillZoneBundle {
-    fn bundle_entity(_: &EntityInstance,
+    fn bundle_entity(entity_instance: &EntityInstance,
                      layer_instance: &LayerInstance,
                      _: Option<&Handle<Image>>,
                      _: Option<&TilesetDefinition>,
@@ -242,6 +780,8 @@ impl LdtkEntity for KillZoneBundle {
         KillZoneBundle {
             sensor: Sensor,
             kill_zone: KillZone,
+            // Kill zones placed in the level are pits unless configured otherwise
+            instant_kill: InstantKill(entity_instance.get_bool_field("instant_kill").copied().unwrap_or(true)),
             collider: Collider::rectangle(layer_instance.grid_size as f32, layer_instance.grid_size as f32),
             rigid_body: RigidBody::Static
         }
@@ -3,6 +3,7 @@ use bevy_ecs_ldtk::prelude::*;
 use bevy_xpbd_2d::prelude::*;
 use crate::world::systems::*;
 use crate::world::components::*;
+use crate::game_state::components::GameState;
 pub mod components;
 pub mod systems;
 
@@ -12,9 +13,17 @@ impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_event::<SpawnPlayerEvent>()
+            .add_event::<SwitchActivated>()
+            .add_event::<DamageEvent>()
+            .add_event::<PlatformActivated>()
+            .add_event::<LevelExitReached>()
             .init_resource::<CoinAnimations>()
             .init_resource::<GameFonts>()
             .init_resource::<GameSounds>()
+            .init_resource::<AudioSettings>()
+            .init_resource::<ActiveCheckpoint>()
+            .init_resource::<CurrentLevel>()
+            .init_resource::<CarriedInventories>()
             .register_type::<TutorialText>()
             .add_systems(Update, add_colliders_to_walls_system)
             .add_systems(Update, add_colliders_to_platforms_system)
@@ -22,10 +31,24 @@ impl Plugin for WorldPlugin {
             .add_systems(Update, setup_coin_system)
             .add_systems(Update, setup_tutorial_text_system)
             .add_systems(Update, setup_score_display_system)
-            .add_systems(Update, move_platforms_system)
+            .add_systems(Update, setup_checkpoint_system)
+            .add_systems(Update, checkpoint_system.run_if(in_state(GameState::Playing)))
+            .add_systems(Startup, setup_screen_fade_system)
+            .add_systems(Update, setup_level_exit_system)
+            .add_systems(Update, update_current_level_system)
+            .add_systems(Update, add_level_bounds_system)
+            .add_systems(Update, update_level_selection_system.after(update_current_level_system).run_if(in_state(GameState::Playing)))
+            .add_systems(Update, level_exit_system.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, level_transition_system.after(update_current_level_system).after(level_exit_system))
+            .add_systems(Update, screen_fade_system.after(level_transition_system))
+            .add_systems(Update, setup_switch_system)
+            .add_systems(Update, switch_system.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, activate_platforms_system.after(switch_system).run_if(in_state(GameState::Playing)))
+            .add_systems(Update, move_platforms_system.after(activate_platforms_system).run_if(in_state(GameState::Playing)))
+            .add_systems(Update, crumbling_platform_system.run_if(in_state(GameState::Playing)))
             .add_systems(Update, update_score_display_system)
-            .add_systems(Update, play_pickup_sound_system)
-            .add_systems(PostUpdate, kill_zone_system)
+            .add_systems(Update, update_music_volume_system)
+            .add_systems(PostUpdate, kill_zone_system.run_if(in_state(GameState::Playing)))
             .add_systems(PostProcessCollisions, one_way_platform_system)
             .register_ldtk_int_cell_for_layer::<WallBundle>("Collision", 1)
             .register_ldtk_int_cell_for_layer::<WallBundle>("Collision", 3)
@@ -35,7 +58,11 @@ impl Plugin for WorldPlugin {
             .register_ldtk_int_cell_for_layer::<BridgeBundle>("Collision", 4)
             .register_ldtk_entity::<PlatformBundle>("Platform")
             .register_ldtk_entity::<PlatformBundle>("BrownPlatform")
-            .register_ldtk_entity::<PlatformBundle>("WrongPlatform")
+            .register_ldtk_entity::<CrumblingPlatformBundle>("WrongPlatform")
+            .register_ldtk_entity::<SwitchBundle>("Switch")
+            .register_ldtk_entity::<CheckpointBundle>("Checkpoint")
+            .register_ldtk_entity::<LevelExitBundle>("LevelExit")
+            .register_ldtk_entity::<CameraZoneBundle>("CameraZone")
             .register_ldtk_entity::<CoinBundle>("Coin")
             .register_ldtk_entity::<PlayerStartPointBundle>("PlayerStartPoint")
             .register_ldtk_entity::<KillZoneBundle>("KillZone")
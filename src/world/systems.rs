@@ -1,11 +1,13 @@
 use bevy::prelude::*;
 use bevy::text::{BreakLineOn, Text2dBounds};
-use bevy_ecs_ldtk::{TileEnumTags};
+use bevy_ecs_ldtk::{EntityIid, TileEnumTags};
+use bevy_ecs_ldtk::prelude::{LdtkProject, LevelIid, LevelMetadataAccessor, LevelSelection, RawLevelAccessor};
 use bevy_spritesheet_animation::component::SpritesheetAnimation;
 use bevy_xpbd_2d::math::{Scalar, Vector};
 use bevy_xpbd_2d::prelude::*;
-use crate::{Player};
-use crate::player::components::CoinCollected;
+use crate::{Inventory, Player};
+use crate::character_controller::components::GroundedOn;
+use crate::player::components::{CoinCollected, Joined, PlayerSlot};
 use crate::world::components::*;
 
 
@@ -30,9 +32,12 @@ pub(crate) fn add_colliders_to_walls_system(
 
 pub fn add_colliders_to_platforms_system(
     mut commands: Commands,
-    platform_query: Query<Entity, (Added<Platform>, Without<Collider>)>,
+    platform_query: Query<(Entity, Option<&PlatformTrigger>), (Added<Platform>, Without<Collider>)>,
 ) {
-    for entity in platform_query.iter() {
+    for (entity, trigger) in platform_query.iter() {
+        if trigger.map_or(true, |trigger| trigger.activation == PlatformActivation::Always) {
+            commands.entity(entity).insert(PlatformActive);
+        }
         commands.entity(entity)
             .insert(RigidBody::Kinematic)
             .insert(OneWayPlatform::default())
@@ -263,55 +268,448 @@ pub fn one_way_platform_system(
     });
 }
 
-pub fn move_platforms_system(
-    mut platform_query: Query<(&mut Transform, &mut LinearVelocity, &mut Path), With<Platform>>
+pub fn setup_checkpoint_system(
+    mut commands: Commands,
+    checkpoint_query: Query<Entity, (Added<Checkpoint>, Without<Collider>)>,
+) {
+    for entity in checkpoint_query.iter() {
+        commands.entity(entity)
+            .insert((
+                Name::new("Checkpoint"),
+                RigidBody::Static,
+                Sensor,
+                Collider::rectangle(16.0, 32.0),
+                CollisionLayers::new(GamePhysicsLayer::Trigger, [GamePhysicsLayer::Player]),
+            ));
+    }
+}
+
+pub fn checkpoint_system(
+    mut checkpoint_query: Query<(&EntityIid, &CollidingEntities, &GlobalTransform, &mut Sprite), With<Checkpoint>>,
+    player_query: Query<(), (With<Player>, Without<IsDead>)>,
+    spawned_checkpoint_query: Query<(), Added<Checkpoint>>,
+    mut active_checkpoint: ResMut<ActiveCheckpoint>,
 ) {
-    for (mut transform, mut linvel, mut path) in platform_query.iter_mut() {
-        if path.points.len() <= 1 { continue; };
+    for (iid, collisions, transform, _) in checkpoint_query.iter() {
+        if !collisions.iter().any(|other| player_query.contains(*other)) { continue; }
+        let iid = iid.to_string();
+        if active_checkpoint.0.as_ref().is_some_and(|checkpoint| checkpoint.iid == iid) { continue; }
+        active_checkpoint.0 = Some(CheckpointLocation {
+            iid,
+            translation: transform.translation(),
+        });
+    }
 
-        let next_point = path.points[path.index];
-        let mut new_velocity =
-            (next_point - transform.translation.truncate()).normalize() * path.speed;
-
-        if new_velocity.dot(linvel.0) < 0. {
-            if path.index == 0 {
-                path.forward = true;
-            } else if path.index == path.points.len() - 1 {
-                path.forward = false;
+    // Checkpoints spawn bright, so levels spawning later need dimming too
+    if !active_checkpoint.is_changed() && spawned_checkpoint_query.is_empty() { return; }
+    // Dim every checkpoint but the active one
+    for (iid, _, _, mut sprite) in checkpoint_query.iter_mut() {
+        let is_active = active_checkpoint.0.as_ref().is_some_and(|checkpoint| checkpoint.iid == iid.to_string());
+        sprite.color = if is_active { Color::WHITE } else { Color::GRAY };
+    }
+}
+
+pub fn setup_screen_fade_system(
+    mut commands: Commands,
+) {
+    commands.spawn((
+        Name::new("Screen Fade"),
+        ScreenFade,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            background_color: Color::BLACK.with_a(0.0).into(),
+            z_index: ZIndex::Global(100),
+            ..default()
+        },
+    ));
+}
+
+pub fn setup_level_exit_system(
+    mut commands: Commands,
+    level_exit_query: Query<Entity, (Added<LevelExit>, Without<Collider>)>,
+) {
+    for entity in level_exit_query.iter() {
+        commands.entity(entity)
+            .insert((
+                Name::new("Level Exit"),
+                RigidBody::Static,
+                Sensor,
+                Collider::rectangle(16.0, 32.0),
+                CollisionLayers::new(GamePhysicsLayer::Trigger, [GamePhysicsLayer::Player]),
+            ));
+    }
+}
+
+pub fn update_current_level_system(
+    level_selection: Res<LevelSelection>,
+    ldtk_project_query: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut current_level: ResMut<CurrentLevel>,
+) {
+    let Ok(ldtk_project) = ldtk_project_query.get_single() else { return };
+    let Some(ldtk_project) = ldtk_project_assets.get(ldtk_project) else { return };
+    let Some(level) = ldtk_project.find_raw_level_by_level_selection(&level_selection) else { return };
+
+    if current_level.0.as_ref() != Some(&level.iid) {
+        current_level.0 = Some(level.iid.clone());
+    }
+}
+
+pub fn add_level_bounds_system(
+    mut commands: Commands,
+    level_query: Query<(Entity, &LevelIid), Added<LevelIid>>,
+    ldtk_project_query: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    if level_query.is_empty() { return; }
+    let Ok(ldtk_project) = ldtk_project_query.get_single() else { return };
+    let Some(ldtk_project) = ldtk_project_assets.get(ldtk_project) else { return };
+
+    for (entity, level_iid) in level_query.iter() {
+        let Some(level) = ldtk_project.get_raw_level_by_iid(&level_iid.to_string()) else { continue };
+        commands.entity(entity).insert(LevelBounds {
+            size: Vec2::new(level.px_wid as f32, level.px_hei as f32),
+        });
+    }
+}
+
+/// Selects the level the players walked into, neighbouring levels are already loaded.
+pub fn update_level_selection_system(
+    level_query: Query<(&LevelIid, &LevelBounds, &GlobalTransform)>,
+    player_query: Query<&GlobalTransform, (With<Player>, Without<IsDead>)>,
+    current_level: Res<CurrentLevel>,
+    level_transition: Option<Res<LevelTransition>>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut active_checkpoint: ResMut<ActiveCheckpoint>,
+) {
+    if level_transition.is_some() { return; }
+
+    let level_bounds: Vec<(String, Rect)> = level_query.iter()
+        .map(|(level_iid, bounds, transform)| (level_iid.to_string(), bounds.rect(transform)))
+        .collect();
+
+    let contains_player = |bounds: &Rect| player_query.iter()
+        .any(|player| bounds.contains(player.translation().truncate()));
+
+    // Stay in the current level as long as one of the players is still in it
+    if level_bounds.iter().any(|(iid, bounds)| current_level.0.as_ref() == Some(iid) && contains_player(bounds)) {
+        return;
+    }
+
+    let Some((iid, _)) = level_bounds.iter().find(|(_, bounds)| contains_player(bounds)) else { return };
+    *level_selection = LevelSelection::iid(iid.clone());
+    // Checkpoints only count in their own level, like with a level exit
+    active_checkpoint.0 = None;
+}
+
+/// Exits to unknown levels are ignored.
+pub fn level_exit_system(
+    level_exit_query: Query<(&LevelExit, &CollidingEntities)>,
+    player_query: Query<(), (With<Player>, Without<IsDead>)>,
+    ldtk_project_query: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    level_transition: Option<Res<LevelTransition>>,
+    mut level_exit_reached: EventWriter<LevelExitReached>,
+) {
+    if level_transition.is_some() { return; }
+    let Ok(ldtk_project) = ldtk_project_query.get_single() else { return };
+    let Some(ldtk_project) = ldtk_project_assets.get(ldtk_project) else { return };
+
+    for (level_exit, collisions) in level_exit_query.iter() {
+        if !collisions.iter().any(|other| player_query.contains(*other)) { continue; }
+        if !ldtk_project.iter_raw_levels().any(|level| level.identifier == level_exit.target_level) { continue; }
+
+        level_exit_reached.send(LevelExitReached {
+            target_level: level_exit.target_level.clone(),
+        });
+        return;
+    }
+}
+
+pub fn level_transition_system(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    level_transition: Option<ResMut<LevelTransition>>,
+    current_level: Res<CurrentLevel>,
+    mut level_selection: ResMut<LevelSelection>,
+    mut active_checkpoint: ResMut<ActiveCheckpoint>,
+    mut carried_inventories: ResMut<CarriedInventories>,
+    player_query: Query<(Entity, &PlayerSlot, &Inventory), With<Player>>,
+    joined_query: Query<&PlayerSlot, With<Joined>>,
+    helper: TransformHelper,
+    start_point_query: Query<Entity, With<PlayerStartPoint>>,
+    parent_query: Query<&Parent>,
+    level_query: Query<&LevelIid>,
+    mut spawn_player_event: EventWriter<SpawnPlayerEvent>,
+) {
+    let Some(mut level_transition) = level_transition else { return };
+    level_transition.timer.tick(time.delta());
+
+    match level_transition.phase {
+        LevelTransitionPhase::FadeOut => {
+            if !level_transition.timer.finished() { return; }
+
+            // The players leave with the old level and come back at the start of the next one
+            carried_inventories.0.clear();
+            for (entity, slot, inventory) in player_query.iter() {
+                carried_inventories.0.insert(*slot, inventory.clone());
+                commands.entity(entity).despawn_recursive();
+            }
+            active_checkpoint.0 = None;
+            *level_selection = LevelSelection::Identifier(level_transition.target_level.clone());
+            level_transition.phase = LevelTransitionPhase::Loading;
+        }
+        LevelTransitionPhase::Loading => {
+            let Some(start_point) = start_point_query.iter()
+                .find(|start_point| current_level.contains(*start_point, &parent_query, &level_query)) else { return };
+            // the start point might have been spawned this frame
+            let Ok(start_point) = helper.compute_global_transform(start_point) else { return };
+
+            for slot in joined_query.iter() {
+                spawn_player_event.send(SpawnPlayerEvent {
+                    translation: start_point.translation(),
+                    slot: *slot,
+                    keep_inventory: false,
+                });
+            }
+            level_transition.phase = LevelTransitionPhase::FadeIn;
+            level_transition.timer.reset();
+        }
+        LevelTransitionPhase::FadeIn => {
+            if level_transition.timer.finished() {
+                commands.remove_resource::<LevelTransition>();
+            }
+        }
+    }
+}
+
+pub fn screen_fade_system(
+    level_transition: Option<Res<LevelTransition>>,
+    mut screen_fade_query: Query<&mut BackgroundColor, With<ScreenFade>>,
+) {
+    let alpha = match level_transition.as_deref() {
+        None => 0.0,
+        Some(level_transition) => match level_transition.phase {
+            LevelTransitionPhase::FadeOut => level_transition.timer.fraction(),
+            LevelTransitionPhase::Loading => 1.0,
+            LevelTransitionPhase::FadeIn => level_transition.timer.fraction_remaining(),
+        },
+    };
+
+    for mut background_color in screen_fade_query.iter_mut() {
+        if background_color.0.a() != alpha {
+            background_color.0.set_a(alpha);
+        }
+    }
+}
+
+pub fn setup_switch_system(
+    mut commands: Commands,
+    switch_query: Query<Entity, (Added<Switch>, Without<Collider>)>,
+) {
+    for entity in switch_query.iter() {
+        commands.entity(entity)
+            .insert((
+                Name::new("Switch"),
+                RigidBody::Static,
+                Sensor,
+                Collider::rectangle(16.0, 16.0),
+                CollisionLayers::new(GamePhysicsLayer::Trigger, [GamePhysicsLayer::Player]),
+            ));
+    }
+}
+
+pub fn switch_system(
+    mut switch_query: Query<(&EntityIid, &CollidingEntities, &mut Switch)>,
+    player_query: Query<(), (With<Player>, Without<IsDead>)>,
+    mut switch_activated_event: EventWriter<SwitchActivated>,
+) {
+    for (iid, collisions, mut switch) in switch_query.iter_mut() {
+        let is_pressed = collisions.iter().any(|other| player_query.contains(*other));
+        if is_pressed && !switch.pressed {
+            switch_activated_event.send(SwitchActivated {
+                iid: iid.to_string(),
+            });
+        }
+        if switch.pressed != is_pressed {
+            switch.pressed = is_pressed;
+        }
+    }
+}
+
+/// Starts triggered platforms and sends them back to their start once nobody rides them.
+pub fn activate_platforms_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut platform_query: Query<(Entity, &mut PlatformTrigger, Has<PlatformActive>, Has<ReturningToStart>), With<Platform>>,
+    player_query: Query<&GroundedOn, With<Player>>,
+    mut switch_activated_events: EventReader<SwitchActivated>,
+    mut platform_activated_event: EventWriter<PlatformActivated>,
+) {
+    let activated_switches: Vec<String> = switch_activated_events.read()
+        .map(|event| event.iid.clone())
+        .collect();
+
+    for (entity, mut trigger, is_active, is_returning) in platform_query.iter_mut() {
+        let is_ridden = player_query.iter().any(|grounded_on| grounded_on.entity == entity);
+        if is_ridden {
+            trigger.idle_time = 0.0;
+        } else {
+            trigger.idle_time += time.delta_seconds();
+        }
+
+        if !is_active {
+            let should_activate = match trigger.activation {
+                PlatformActivation::Always => true,
+                PlatformActivation::OnStand => is_ridden,
+                PlatformActivation::OnSwitch => trigger.switch_iid.as_ref()
+                    .is_some_and(|switch_iid| activated_switches.contains(switch_iid)),
+            };
+            if should_activate {
+                // Idle time only counts towards returning once the platform moved
+                trigger.idle_time = 0.0;
+                commands.entity(entity).insert(PlatformActive);
+                platform_activated_event.send(PlatformActivated { platform: entity });
+            }
+            continue;
+        }
+
+        let Some(return_delay) = trigger.return_delay else { continue };
+        if trigger.activation != PlatformActivation::Always && !is_returning && trigger.idle_time >= return_delay {
+            commands.entity(entity).insert(ReturningToStart);
+        }
+    }
+}
+
+pub fn crumbling_platform_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut platform_query: Query<(Entity, &mut CrumblingPlatform, &mut Transform, &mut LinearVelocity)>,
+    player_query: Query<&GroundedOn, With<Player>>,
+) {
+    for (entity, mut crumbling_platform, mut transform, mut linvel) in platform_query.iter_mut() {
+        let origin = *crumbling_platform.origin.get_or_insert(transform.translation);
+
+        match crumbling_platform.state {
+            CrumbleState::Intact => {
+                // Only an uninterrupted stand crumbles the platform
+                if !player_query.iter().any(|grounded_on| grounded_on.entity == entity) {
+                    crumbling_platform.timer = 0.0;
+                    continue;
+                }
+                crumbling_platform.timer += time.delta_seconds();
+                if crumbling_platform.timer >= crumbling_platform.crumble_delay {
+                    crumbling_platform.state = CrumbleState::Shaking;
+                    crumbling_platform.timer = 0.0;
+                }
             }
+            CrumbleState::Shaking => {
+                crumbling_platform.timer += time.delta_seconds();
+                transform.translation.x = origin.x + (crumbling_platform.timer * 60.0).sin();
 
-            transform.translation.x = path.points[path.index].x;
-            transform.translation.y = path.points[path.index].y;
+                if crumbling_platform.timer >= crumbling_platform.shake_duration {
+                    transform.translation = origin;
+                    commands.entity(entity).insert((
+                        RigidBody::Dynamic,
+                        LockedAxes::ROTATION_LOCKED,
+                        GravityScale(30.0),
+                    ));
+                    crumbling_platform.state = CrumbleState::Falling;
+                    crumbling_platform.timer = 0.0;
+                }
+            }
+            CrumbleState::Falling => {
+                crumbling_platform.timer += time.delta_seconds();
+                if crumbling_platform.timer >= crumbling_platform.respawn_delay {
+                    transform.translation = origin;
+                    linvel.0 = Vec2::ZERO;
+                    commands.entity(entity).insert(RigidBody::Kinematic);
+                    crumbling_platform.state = CrumbleState::Intact;
+                    crumbling_platform.timer = 0.0;
+                }
+            }
+        }
+    }
+}
 
-            if path.forward {
-                path.index += 1;
+pub fn move_platforms_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut platform_query: Query<(Entity, &mut Transform, &mut LinearVelocity, &mut Path, Has<ReturningToStart>), (With<Platform>, With<PlatformActive>)>
+) {
+    let delta_time = time.delta_seconds();
+
+    for (entity, mut transform, mut linvel, mut path, is_returning) in platform_query.iter_mut() {
+        if is_returning && !path.points.is_empty() {
+            let position = transform.translation.truncate();
+            let start_point = path.points[0];
+            if position.distance(start_point) <= path.speed * delta_time {
+                transform.translation.x = start_point.x;
+                transform.translation.y = start_point.y;
+                path.reset();
+                linvel.0 = Vec2::ZERO;
+                commands.entity(entity).remove::<(PlatformActive, ReturningToStart)>();
             } else {
-                path.index -= 1;
+                linvel.0 = (start_point - position).normalize() * path.speed;
             }
+            continue;
+        }
 
-            new_velocity =
-                (path.points[path.index] - transform.translation.truncate()).normalize() * path.speed;
+        if path.points.len() <= 1 || path.finished {
+            linvel.0 = Vec2::ZERO;
+            continue;
+        };
+
+        if path.wait_timer > 0.0 {
+            path.wait_timer -= delta_time;
+            linvel.0 = Vec2::ZERO;
+            continue;
         }
 
-        linvel.0 = new_velocity;
+        let position = transform.translation.truncate();
+        let next_point = path.points[path.index];
+        let speed = path.speed * path.easing.speed_factor(path.segment_progress(position));
+
+        // Snap to the point instead of overshooting it
+        if position.distance(next_point) <= speed * delta_time {
+            transform.translation.x = next_point.x;
+            transform.translation.y = next_point.y;
+
+            path.wait_timer = path.wait_time(path.index);
+            path.advance();
+            if path.wait_timer > 0.0 || path.finished {
+                linvel.0 = Vec2::ZERO;
+                continue;
+            }
+        }
+
+        let position = transform.translation.truncate();
+        let next_point = path.points[path.index];
+        let speed = path.speed * path.easing.speed_factor(path.segment_progress(position));
+        linvel.0 = (next_point - position).normalize_or_zero() * speed;
     }
 }
 
 pub fn kill_zone_system(
-    mut commands: Commands,
-    kill_zone_query: Query<&CollidingEntities, With<KillZone>>,
-    mut player_query: Query<(Entity, &mut CollisionLayers), (With<Player>, Without<IsDead>)>,
-    game_sounds: Res<GameSounds>
+    kill_zone_query: Query<(&CollidingEntities, &GlobalTransform, Option<&InstantKill>), With<KillZone>>,
+    player_query: Query<&GlobalTransform, (With<Player>, Without<IsDead>)>,
+    mut damage_event: EventWriter<DamageEvent>,
 ) {
-    for collisions in kill_zone_query.iter() {
+    for (collisions, kill_zone_transform, instant_kill) in kill_zone_query.iter() {
         for other in collisions.iter() {
-            let Ok((_, mut collision_layers)) = player_query.get_mut(*other) else { continue };
-            collision_layers.memberships = LayerMask::from(GamePhysicsLayer::Dead);
-
-            commands.entity(*other).insert(IsDead);
-            commands.spawn(AudioBundle {
-                source: game_sounds.player_hurt.clone(),
-                settings: PlaybackSettings::DESPAWN,
+            let Ok(player_transform) = player_query.get(*other) else { continue };
+            // Push the player away from the kill zone and slightly upwards
+            let away = (player_transform.translation().x - kill_zone_transform.translation().x).signum();
+            damage_event.send(DamageEvent {
+                target: *other,
+                amount: 1,
+                knockback: Vec2::new(away, 1.0).normalize(),
+                instant_kill: instant_kill.is_some_and(|instant_kill| instant_kill.0),
             });
         }
     }
@@ -332,15 +730,15 @@ pub fn update_score_display_system(
     };
 }
 
-pub fn play_pickup_sound_system(
-    mut commands: Commands,
-    game_sounds: Res<GameSounds>,
-    mut coin_collected_events: EventReader<CoinCollected>,
+/// Follows volume changes while the music is playing.
+pub fn update_music_volume_system(
+    audio_settings: Res<AudioSettings>,
+    music_query: Query<&AudioSink, With<BackgroundMusic>>,
 ) {
-    for _ in coin_collected_events.read() {
-        commands.spawn(AudioBundle {
-            source: game_sounds.coin_collected.clone(),
-            settings: PlaybackSettings::DESPAWN,
-        });
+    let volume = audio_settings.effective_music_volume();
+    for sink in music_query.iter() {
+        if sink.volume() != volume {
+            sink.set_volume(volume);
+        }
     }
 }
\ No newline at end of file